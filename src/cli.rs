@@ -16,6 +16,10 @@ use clap::{
 use clap_complete::Shell;
 use std::{ffi::OsString, io};
 
+/// Seconds to wait for the applications of a project to exit when stopping it, before they are
+/// forcefully terminated
+const DEFAULT_STOP_TIMEOUT: u64 = 5;
+
 /// Main CLI entry type
 #[derive(Parser)]
 #[clap(
@@ -42,6 +46,9 @@ pub(crate) enum Commands {
     /// Manage layouts which can be used in projects
     #[clap(subcommand)]
     Layout(LayoutCommands),
+    /// Manage fragments of applications which can be included in projects
+    #[clap(subcommand)]
+    Fragment(FragmentCommands),
     /// Generate shell completions for i3nator
     GenerateShellCompletions {
         /// Shell to generate the completions for
@@ -109,6 +116,20 @@ pub(crate) enum ProjectCommands {
         /// Workspace to apply the layout to. This overrides the specified workspace in the project's configuration.
         #[clap(short = 'w', long = "workspace", value_name = "WORKSPACE")]
         workspace: Option<String>,
+        /// Set the value of a variable declared in the project's `[variables]`. Can be specified multiple times.
+        #[clap(long = "set", value_name = "NAME=VALUE", value_parser = parse_variable)]
+        variables: Vec<(String, String)>,
+        /// Only start the applications with the given names, separated by commas
+        #[clap(
+            long = "only",
+            value_name = "NAMES",
+            value_delimiter = ',',
+            conflicts_with = "skip"
+        )]
+        only: Vec<String>,
+        /// Don't start the applications with the given names, separated by commas
+        #[clap(long = "skip", value_name = "NAMES", value_delimiter = ',')]
+        skip: Vec<String>,
     },
     /// Create a new project and open it in your editor
     New {
@@ -134,11 +155,35 @@ pub(crate) enum ProjectCommands {
         #[clap(long = "no-verify")]
         no_verify: bool,
     },
+    /// Create a new project from the applications currently open on a workspace
+    Snapshot {
+        /// Name of the project to create
+        name: OsString,
+        /// Workspace to capture. If not specified, the focused workspace will be used.
+        #[clap(short = 'w', long = "workspace", value_name = "WORKSPACE")]
+        workspace: Option<String>,
+    },
     /// Start a project according to it's configuration
     #[clap(alias = "run")]
     Start {
         /// Name of the project to start
         name: OsString,
+        /// Start another instance of the project, even if it is already running
+        #[clap(long = "force", conflicts_with = "restart")]
+        force: bool,
+        /// Stop the project first if it is already running
+        #[clap(long = "restart")]
+        restart: bool,
+        /// Seconds to wait for the applications of the running project to exit when restarting it, before they are
+        /// forcefully terminated
+        #[clap(
+            short = 't',
+            long = "timeout",
+            value_name = "SECONDS",
+            default_value_t = DEFAULT_STOP_TIMEOUT,
+            requires = "restart"
+        )]
+        timeout: u64,
         /// Directory used as context for starting the applications. This overrides any specified working-directory in
         /// the project's configuration.
         #[clap(short = 'd', long = "working-directory", value_name = "PATH")]
@@ -146,6 +191,40 @@ pub(crate) enum ProjectCommands {
         /// Workspace to apply the layout to. This overrides the specified workspace in the project's configuration.
         #[clap(short = 'w', long = "workspace", value_name = "WORKSPACE")]
         workspace: Option<String>,
+        /// Set the value of a variable declared in the project's `[variables]`. Can be specified multiple times.
+        #[clap(long = "set", value_name = "NAME=VALUE", value_parser = parse_variable)]
+        variables: Vec<(String, String)>,
+        /// Only start the applications with the given names, separated by commas
+        #[clap(
+            long = "only",
+            value_name = "NAMES",
+            value_delimiter = ',',
+            conflicts_with = "skip"
+        )]
+        only: Vec<String>,
+        /// Don't start the applications with the given names, separated by commas
+        #[clap(long = "skip", value_name = "NAMES", value_delimiter = ',')]
+        skip: Vec<String>,
+    },
+    /// Show which projects are running and which of their applications are still alive
+    Status {
+        /// Name of the project to show the status for.
+        ///
+        /// If not specified, the status of all running projects will be shown.
+        name: Option<OsString>,
+    },
+    /// Stop a running project, closing all applications it started
+    Stop {
+        /// Name of the project to stop
+        name: OsString,
+        /// Seconds to wait for the applications to exit before they are forcefully terminated
+        #[clap(
+            short = 't',
+            long = "timeout",
+            value_name = "SECONDS",
+            default_value_t = DEFAULT_STOP_TIMEOUT
+        )]
+        timeout: u64,
     },
     /// Verify the configuration of the existing projects
     Verify {
@@ -168,6 +247,9 @@ pub(crate) enum LayoutCommands {
         /// Don't open the new layout for editing after copying
         #[clap(long = "no-edit")]
         no_edit: bool,
+        /// Don't verify the contents of the new layout after the editor closes
+        #[clap(long = "no-verify")]
+        no_verify: bool,
     },
     /// Delete existing layouts
     #[clap(alias = "remove")]
@@ -180,6 +262,9 @@ pub(crate) enum LayoutCommands {
     Edit {
         /// Name of the layout to edit
         name: OsString,
+        /// Don't verify the contents of the layout after the editor closes
+        #[clap(long = "no-verify")]
+        no_verify: bool,
     },
     /// Show information for the specified layout
     Info {
@@ -199,6 +284,9 @@ pub(crate) enum LayoutCommands {
         /// Don't open the new layout for editing
         #[clap(long = "no-edit")]
         no_edit: bool,
+        /// Don't verify the contents of the new layout after the editor closes
+        #[clap(long = "no-verify")]
+        no_verify: bool,
         /// Prepopulate the layout from the given path. Use '-' to read from stdin.
         #[clap(short = 't', long = "template")]
         template: Option<OsString>,
@@ -212,7 +300,63 @@ pub(crate) enum LayoutCommands {
         /// Open the renamed layout for editing
         #[clap(long = "edit")]
         edit: bool,
+        /// Don't verify the contents of the renamed layout after the editor closes
+        #[clap(long = "no-verify")]
+        no_verify: bool,
+    },
+    /// Save the layout of a workspace, including the windows it contains, as a new layout
+    Save {
+        /// Name of the layout to create
+        name: OsString,
+        /// Workspace to save the layout of. If not specified, the focused workspace will be used.
+        #[clap(short = 'w', long = "workspace", value_name = "WORKSPACE")]
+        workspace: Option<String>,
+    },
+}
+
+/// Fragment-specific subcommands
+#[derive(Subcommand)]
+pub(crate) enum FragmentCommands {
+    /// Delete existing fragments
+    #[clap(alias = "remove")]
+    Delete {
+        /// Names of the fragments to delete
+        #[clap(required = true)]
+        names: Vec<OsString>,
     },
+    /// Open an existing fragment in your editor
+    Edit {
+        /// Name of the fragment to edit
+        name: OsString,
+        /// Don't verify the contents of the fragment after the editor closes
+        #[clap(long = "no-verify")]
+        no_verify: bool,
+    },
+    /// List all fragments
+    List {
+        /// List one fragment per line, no other output
+        #[clap(short = 'q', long = "quiet")]
+        quiet: bool,
+    },
+    /// Create a new fragment and open it in your editor
+    New {
+        /// Name of the fragment to create
+        name: OsString,
+        /// Don't open the new fragment for editing
+        #[clap(long = "no-edit")]
+        no_edit: bool,
+        /// Don't verify the contents of the new fragment after the editor closes
+        #[clap(long = "no-verify")]
+        no_verify: bool,
+    },
+}
+
+/// Parse a variable given as `NAME=VALUE`
+fn parse_variable(variable: &str) -> Result<(String, String), String> {
+    variable
+        .split_once('=')
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .ok_or_else(|| format!("expected `NAME=VALUE`, got '{}'", variable))
 }
 
 /// Generate shell completions
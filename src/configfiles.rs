@@ -18,7 +18,7 @@ use std::{
 };
 
 lazy_static! {
-    static ref XDG_DIRS: xdg::BaseDirectories =
+    pub(crate) static ref XDG_DIRS: xdg::BaseDirectories =
         xdg::BaseDirectories::with_prefix("i3nator").expect("couldn't get XDG base directory");
 }
 
@@ -184,6 +184,17 @@ pub trait ConfigFile: Sized {
     /// - `Ok`: nothing (`()`) if the verification succeeded.
     /// - `Err`: an error if the configuration could not be parsed with details on what failed.
     fn verify(&self) -> Result<()>;
+
+    /// Check the configuration for likely mistakes which don't prevent it from being used, e.g.
+    /// layout placeholders that will never swallow any of the started applications.
+    ///
+    /// Configurations that fail [`ConfigFile::verify`][fn-ConfigFile-verify] don't produce any
+    /// warnings.
+    ///
+    /// [fn-ConfigFile-verify]: #tymethod.verify
+    fn warnings(&self) -> Vec<String> {
+        vec![]
+    }
 }
 
 /// Helping type to consolidate common functionality between projects and layouts.
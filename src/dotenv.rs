@@ -0,0 +1,229 @@
+// Copyright Pit Kleyersburg <pitkley@googlemail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified or distributed
+// except according to those terms.
+
+use crate::errors::*;
+use std::{
+    fs::File,
+    io::{prelude::*, BufReader},
+    path::Path,
+};
+
+// Parser for files in dotenv syntax, i.e. lines of `KEY=VALUE` pairs, optionally prefixed with
+// `export`. Values can be unquoted, single-quoted (taken literally) or double-quoted (supporting
+// escape sequences). Comments start with `#`, either at the beginning of a line or after a
+// whitespace following an unquoted value.
+
+/// Load the variables defined in the dotenv file at `path`, in the order they are defined in.
+pub fn from_path(path: &Path) -> Result<Vec<(String, String)>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    parse(&contents).map_err(|(line, message)| {
+        ErrorKind::InvalidEnvFile(path.to_string_lossy().into_owned(), line, message).into()
+    })
+}
+
+fn parse(contents: &str) -> ::std::result::Result<Vec<(String, String)>, (usize, String)> {
+    let mut variables = vec![];
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| (index + 1, "expected `KEY=VALUE`".to_owned()))?;
+
+        let key = key.trim_end();
+        let first = key.chars().next();
+        let valid_key = matches!(first, Some(ch) if ch.is_ascii_alphabetic() || ch == '_')
+            && key
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '.');
+        if !valid_key {
+            return Err((index + 1, format!("invalid variable name '{}'", key)));
+        }
+
+        let value = parse_value(value.trim_start()).map_err(|message| (index + 1, message))?;
+        variables.push((key.to_owned(), value));
+    }
+
+    Ok(variables)
+}
+
+fn parse_value(value: &str) -> ::std::result::Result<String, String> {
+    let mut chars = value.chars();
+    let (parsed, rest) = match chars.next() {
+        Some('\'') => {
+            let end = chars
+                .as_str()
+                .find('\'')
+                .ok_or_else(|| "unterminated single-quoted value".to_owned())?;
+            let (parsed, rest) = chars.as_str().split_at(end);
+            (parsed.to_owned(), &rest[1..])
+        }
+        Some('"') => {
+            let mut parsed = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => parsed.push('\n'),
+                        Some('r') => parsed.push('\r'),
+                        Some('t') => parsed.push('\t'),
+                        Some(ch) => parsed.push(ch),
+                        None => return Err("unterminated double-quoted value".to_owned()),
+                    },
+                    Some(ch) => parsed.push(ch),
+                    None => return Err("unterminated double-quoted value".to_owned()),
+                }
+            }
+            (parsed, chars.as_str())
+        }
+        _ => {
+            // Unquoted values end at a comment
+            let end = value
+                .find(" #")
+                .or_else(|| value.find("\t#"))
+                .unwrap_or(value.len());
+            return Ok(value[..end].trim_end().to_owned());
+        }
+    };
+
+    // Only a comment may follow a quoted value
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(parsed)
+    } else {
+        Err(format!("unexpected '{}' after quoted value", rest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|&(key, value)| (key.to_owned(), value.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_blank_lines_and_comments() {
+        assert_eq!(parse("").unwrap(), vec![]);
+        assert_eq!(
+            parse("\n# comment\n  \n\tKEY=value\n   # indented comment\n").unwrap(),
+            variables(&[("KEY", "value")])
+        );
+    }
+
+    #[test]
+    fn parse_export() {
+        assert_eq!(
+            parse("export KEY=value\nexport   OTHER = other\nexported=value").unwrap(),
+            variables(&[("KEY", "value"), ("OTHER", "other"), ("exported", "value")])
+        );
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(
+            parse("_KEY=1\nkey.name_2=2\nEMPTY=").unwrap(),
+            variables(&[("_KEY", "1"), ("key.name_2", "2"), ("EMPTY", "")])
+        );
+    }
+
+    #[test]
+    fn parse_unquoted() {
+        assert_eq!(parse_value("value").unwrap(), "value");
+        assert_eq!(parse_value("two words  ").unwrap(), "two words");
+        assert_eq!(parse_value("value # comment").unwrap(), "value");
+        assert_eq!(parse_value("value\t# comment").unwrap(), "value");
+        assert_eq!(parse_value("value#fragment").unwrap(), "value#fragment");
+        assert_eq!(
+            parse_value(r#"it's "literal""#).unwrap(),
+            r#"it's "literal""#
+        );
+    }
+
+    #[test]
+    fn parse_single_quoted() {
+        assert_eq!(parse_value("'value'").unwrap(), "value");
+        assert_eq!(
+            parse_value("'  spaces # kept  '").unwrap(),
+            "  spaces # kept  "
+        );
+        assert_eq!(parse_value(r"'no \n escapes'").unwrap(), r"no \n escapes");
+        assert_eq!(parse_value("'value' # comment").unwrap(), "value");
+        assert_eq!(parse_value("''").unwrap(), "");
+    }
+
+    #[test]
+    fn parse_double_quoted() {
+        assert_eq!(parse_value(r#""value""#).unwrap(), "value");
+        assert_eq!(parse_value(r#""a # b""#).unwrap(), "a # b");
+        assert_eq!(
+            parse_value(r#""line\nbreak\ttab\rreturn""#).unwrap(),
+            "line\nbreak\ttab\rreturn"
+        );
+        assert_eq!(
+            parse_value(r#""\"quoted\" \\ \$HOME""#).unwrap(),
+            r#""quoted" \ $HOME"#
+        );
+        assert_eq!(parse_value(r#""value"  # comment"#).unwrap(), "value");
+    }
+
+    #[test]
+    fn parse_invalid_values() {
+        assert_eq!(
+            parse_value("'unterminated").unwrap_err(),
+            "unterminated single-quoted value"
+        );
+        assert_eq!(
+            parse_value(r#""unterminated"#).unwrap_err(),
+            "unterminated double-quoted value"
+        );
+        assert_eq!(
+            parse_value(r#""escaped\"#).unwrap_err(),
+            "unterminated double-quoted value"
+        );
+        assert_eq!(
+            parse_value("'value' trailing").unwrap_err(),
+            "unexpected 'trailing' after quoted value"
+        );
+    }
+
+    #[test]
+    fn parse_invalid_lines() {
+        assert_eq!(
+            parse("KEY=value\n\nmalformed line").unwrap_err(),
+            (3, "expected `KEY=VALUE`".to_owned())
+        );
+        assert_eq!(
+            parse("1KEY=value").unwrap_err(),
+            (1, "invalid variable name '1KEY'".to_owned())
+        );
+        assert_eq!(
+            parse("MY-KEY=value").unwrap_err(),
+            (1, "invalid variable name 'MY-KEY'".to_owned())
+        );
+        assert_eq!(
+            parse("=value").unwrap_err(),
+            (1, "invalid variable name ''".to_owned())
+        );
+        assert_eq!(
+            parse("KEY=value\nOTHER='unterminated").unwrap_err(),
+            (2, "unterminated single-quoted value".to_owned())
+        );
+    }
+}
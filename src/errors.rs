@@ -23,11 +23,32 @@ error_chain! {
         IoError(::std::io::Error)
             #[doc = "Error mapping to `std::io::Error`."];
 
+        JsonError(::serde_json::Error)
+            #[doc = "Error caused by `serde_json`, on serializing or deserializing JSON."];
+
+        RegexError(::regex::Error)
+            #[doc = "Error caused by `regex`, on compiling a regular expression."];
+
         Utf8Error(::std::str::Utf8Error)
             #[doc = "Error mapping to `std::str::Utf8Error`."];
 
         TomlError(::toml::de::Error)
             #[doc = "Error caused by `toml`, on deserializing using Serde."];
+
+        TomlSerError(::toml::ser::Error)
+            #[doc = "Error caused by `toml`, on serializing using Serde."];
+
+        X11ConnectError(::x11rb::errors::ConnectError)
+            #[doc = "Error caused by `x11rb`, on establishing a connection to the X server."];
+
+        X11ConnectionError(::x11rb::errors::ConnectionError)
+            #[doc = "Error caused by `x11rb`, on sending a request to the X server."];
+
+        X11ReplyError(::x11rb::errors::ReplyError)
+            #[doc = "Error caused by `x11rb`, on receiving the reply to a request."];
+
+        X11ReplyOrIdError(::x11rb::errors::ReplyOrIdError)
+            #[doc = "Error caused by `x11rb`, on generating the ID of a new resource."];
     }
 
     errors {
@@ -56,6 +77,13 @@ error_chain! {
             display("config of type '{}' already exists: '{}'", p, t)
         }
 
+        /// An error that occurs if a project extends itself, either directly or through other
+        /// projects.
+        CyclicExtends(t: String) {
+            description("project extends itself")
+            display("project extends itself: {}", t)
+        }
+
         /// An error that occurs when the default editor is not specified.
         ///
         /// One of the environment variables `$VISUAL` or `$EDITOR` has to be set.
@@ -64,28 +92,130 @@ error_chain! {
             display("cannot find an editor. Please specify $VISUAL or $EDITOR")
         }
 
+        /// An error that occurs if a project has neither a layout nor applications to start, on
+        /// any workspace.
+        EmptyProject(t: String) {
+            description("project is empty")
+            display("project '{}' has neither a layout nor applications", t)
+        }
+
+        /// An error that occurs if an exec backend requiring a `target` is used without one.
+        ExecTargetMissing(t: String) {
+            description("exec target is missing")
+            display("the '{}' exec backend requires a `target`", t)
+        }
+
+        /// An error that occurs if a hook command fails or doesn't finish within its timeout.
+        HookFailed(c: String, t: String) {
+            description("hook failed")
+            display("hook '{}' failed: {}", c, t)
+        }
+
+        /// An error that occurs if the dependencies of an application can't be satisfied, e.g.
+        /// because they are unknown or cyclic.
+        InvalidDependency(a: String, t: String) {
+            description("dependency is invalid")
+            display("application '{}' has an invalid dependency: {}", a, t)
+        }
+
+        /// An error that occurs if an environment file is not valid dotenv syntax.
+        InvalidEnvFile(p: String, line: usize, t: String) {
+            description("env file is invalid")
+            display("env file '{}' is invalid at line {}: {}", p, line, t)
+        }
+
+        /// An error that occurs if a layout is not valid, e.g. if its JSON is malformed or contains
+        /// invalid nodes.
+        InvalidLayout(line: usize, column: usize, t: String) {
+            description("layout is invalid")
+            display("layout is invalid at line {}, column {}: {}", line, column, t)
+        }
+
         /// An error that occurs when a `Path` (i.e. `OsStr`) cannot be converted to UTF8.
         InvalidUtF8Path(t: String) {
             description("path is invalid UTF8")
             display("path is invalid UTF8: '{}'", t)
         }
 
+        /// An error that occurs if an application didn't request the text placed in a selection
+        /// for it to paste.
+        PasteNotRequested {
+            description("pasted text was not requested")
+            display("the application didn't request the pasted text in time")
+        }
+
         /// An error that occurs if a specified path does not exist.
         PathDoesntExist(t: String) {
             description("path doesn't exist")
             display("path doesn't exist: '{}'", t)
         }
 
+        /// An error that occurs if a program that is required for an operation could not be found.
+        ProgramNotFound(t: String) {
+            description("program not found")
+            display("program '{}' could not be found, is it installed?", t)
+        }
+
+        /// An error that occurs if a project is expected to be running, but no runtime state for it
+        /// has been recorded.
+        ProjectNotRunning(t: String) {
+            description("project is not running")
+            display("project is not running: '{}'", t)
+        }
+
+        /// An error that occurs if applications could not be sent a signal, listing the error for
+        /// every process id.
+        SignalFailed(t: String) {
+            description("signalling applications failed")
+            display("signalling applications failed: {}", t)
+        }
+
         /// An error that occurs if text or key-presses could not be input into an application.
         TextOrKeyInputFailed {
             description("text or key input failed")
             display("inputting text or key-presses into an application failed")
         }
 
+        /// An error that occurs if a variable is used that has no default and was not set.
+        UndefinedVariable(t: String, d: Option<String>) {
+            description("variable is not set")
+            display(
+                "variable '{}' is required but not set, use `--set {}=<value>`{}",
+                t,
+                t,
+                d.as_ref().map(|d| format!(" ({})", d)).unwrap_or_default()
+            )
+        }
+
+        /// An error that occurs if an application is referred to by a name no application of the
+        /// project has.
+        UnknownApplication(t: String) {
+            description("application is unknown")
+            display("application is unknown: '{}'", t)
+        }
+
+        /// An error that occurs if a key to simulate has an unknown name.
+        UnknownKey(t: String) {
+            description("key is unknown")
+            display("key is unknown: '{}'", t)
+        }
+
         /// An error that occurs if a project does not exist under a specified name.
         UnknownConfig(p: String, t: String) {
             description("config is unknown")
             display("config of type '{}' is unknown: '{}'", p, t)
         }
+
+        /// An error that occurs if a variable is set that is not declared by the project.
+        UnknownVariable(t: String) {
+            description("variable is unknown")
+            display("variable is not declared in `[variables]`: '{}'", t)
+        }
+
+        /// An error that occurs if a workspace does not exist under a specified name.
+        UnknownWorkspace(t: String) {
+            description("workspace is unknown")
+            display("workspace is unknown: '{}'", t)
+        }
     }
 }
@@ -0,0 +1,927 @@
+// Copyright Pit Kleyersburg <pitkley@googlemail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified or distributed
+// except according to those terms.
+
+//! Module for inputting text and key-presses into started applications.
+//!
+//! The input is done by a [`Backend`](trait.Backend.html), either natively through the XTEST
+//! extension of the X server, by running `xdotool`, or through the remote control of terminals
+//! (tmux, kitty and wezterm).
+
+use crate::{
+    errors::*,
+    tree,
+    types::{Exec, ExecBackend, ExecStep, ExecType, Paste, PasteSelection},
+    x11::{self, Keysym, Window},
+};
+use i3ipc::I3Connection;
+use regex::Regex;
+use std::{
+    io::{self, Read, Seek, SeekFrom, Write},
+    process::{Command, ExitStatus, Stdio},
+    slice, thread,
+    time::{Duration, Instant},
+};
+use wait_timeout::ChildExt;
+
+/// A way of inputting text and key-presses into the window of an application.
+pub(crate) trait Backend {
+    /// Input `text` into the window.
+    fn text(&mut self, text: &str) -> Result<()>;
+
+    /// Press the given keys one after another. Every key is given in the format `xdotool key`
+    /// expects, e.g. `Return` or `ctrl+shift+t`.
+    ///
+    /// By default, the characters a terminal would receive for the keys are input as text.
+    fn keys(&mut self, keys: &[String]) -> Result<()> {
+        for key in keys {
+            self.text(&key_sequence(key)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Paste `text` into the window, failing if it isn't pasted within `timeout`.
+    ///
+    /// By default, the text is placed in the X selection configured in `paste` and the paste keys
+    /// are pressed, see [`paste_through`](fn.paste_through.html).
+    fn paste(&mut self, text: &str, paste: &Paste, timeout: Duration) -> Result<()> {
+        let mut selection = X11Selection {
+            selection: x11::Selection::connect(paste.selection)?,
+            kind: paste.selection,
+            timeout,
+        };
+        paste_through(&mut selection, text, || {
+            self.keys(slice::from_ref(&paste.keys))
+        })
+    }
+}
+
+/// An X selection (e.g. the clipboard) text can be pasted from.
+pub trait Selection {
+    /// Get the text contents of the selection, or `None` if it isn't owned by anyone or doesn't
+    /// contain text.
+    fn read(&mut self) -> Result<Option<Vec<u8>>>;
+
+    /// Own the selection, providing the text `contents`.
+    fn write(&mut self, contents: &[u8]) -> Result<()>;
+
+    /// Wait for an application to request the contents written last, returning whether one did.
+    fn wait_for_request(&mut self) -> Result<bool>;
+
+    /// Own the selection again with its previous text `contents`, providing them after this
+    /// returns.
+    fn restore(&mut self, contents: &[u8]) -> Result<()>;
+
+    /// Clear the selection, such that it isn't owned by anyone.
+    fn clear(&mut self) -> Result<()>;
+}
+
+/// Paste `text` by placing it in `selection` and pressing the paste keys using `press_keys`,
+/// failing if the application doesn't request the text.
+///
+/// The previous contents of the selection are only restored once the text was requested, or
+/// waiting for the request failed, such that the application can't paste them instead. They are
+/// restored even if pressing the keys failed. If the selection wasn't owned by anyone or didn't
+/// contain text, it is cleared instead.
+pub fn paste_through<S, F>(selection: &mut S, text: &str, press_keys: F) -> Result<()>
+where
+    S: Selection + ?Sized,
+    F: FnOnce() -> Result<()>,
+{
+    let previous = selection.read()?;
+    selection.write(text.as_bytes())?;
+    let result = press_keys().and_then(|()| {
+        if selection.wait_for_request()? {
+            Ok(())
+        } else {
+            Err(ErrorKind::PasteNotRequested.into())
+        }
+    });
+
+    let restored = match previous {
+        Some(ref previous) => selection.restore(previous),
+        None => selection.clear(),
+    };
+
+    result.and(restored)
+}
+
+/// An X selection owned through a connection to the X server, waiting at most `timeout` for
+/// other applications.
+struct X11Selection {
+    selection: x11::Selection,
+    kind: PasteSelection,
+    timeout: Duration,
+}
+
+impl Selection for X11Selection {
+    fn read(&mut self) -> Result<Option<Vec<u8>>> {
+        self.selection.read(self.timeout)
+    }
+
+    fn write(&mut self, contents: &[u8]) -> Result<()> {
+        self.selection.own(contents)
+    }
+
+    fn wait_for_request(&mut self) -> Result<bool> {
+        self.selection
+            .serve(Instant::now().checked_add(self.timeout))
+    }
+
+    fn restore(&mut self, contents: &[u8]) -> Result<()> {
+        // The contents have to be provided until another application owns the selection, even
+        // once i3nator exits
+        let mut selection = x11::Selection::connect(self.kind)?;
+        selection.own(contents)?;
+        selection.serve_detached()
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.selection.clear()
+    }
+}
+
+/// Create the backend configured in `exec` for the application with the process id `pid`.
+///
+/// Backends inputting into the window of the application wait for it to be opened, while backends
+/// relying on the terminal wait for it to accept commands.
+pub(crate) fn backend(exec: &Exec, pid: u32, container: Option<i64>) -> Result<Box<dyn Backend>> {
+    let target = |backend: &str| {
+        exec.target
+            .as_deref()
+            .ok_or_else(|| ErrorKind::ExecTargetMissing(backend.to_owned()))
+    };
+
+    Ok(match exec.backend {
+        ExecBackend::X11 => Box::new(X11::new(pid, container, exec.timeout)?),
+        ExecBackend::Xdotool => Box::new(Xdotool::new(pid, exec.timeout)),
+        ExecBackend::Tmux => Box::new(Tmux::new(target("tmux")?, exec.timeout)?),
+        ExecBackend::Kitty => Box::new(Kitty::new(target("kitty")?, exec.timeout)?),
+        ExecBackend::Wezterm => Box::new(Wezterm::new(target("wezterm")?, exec.timeout)?),
+    })
+}
+
+/// Verify that the commands of `exec` can be input, i.e. that its backend has a target if it
+/// requires one.
+pub(crate) fn verify(exec: &Exec) -> Result<()> {
+    let backend = match exec.backend {
+        ExecBackend::Tmux => "tmux",
+        ExecBackend::Kitty => "kitty",
+        ExecBackend::Wezterm => "wezterm",
+        _ => return Ok(()),
+    };
+
+    match exec.target {
+        Some(_) => Ok(()),
+        None => Err(ErrorKind::ExecTargetMissing(backend.to_owned()).into()),
+    }
+}
+
+/// Input the commands and steps of `exec` into the window of the application with the process
+/// id `pid`, held by the i3 container `container` if it is known.
+///
+/// `title_matches` waits for the title of the window to match a regular expression, returning
+/// whether it did. If it didn't, the remaining commands and steps are not input and the regular
+/// expression is returned.
+pub(crate) fn exec_commands<F>(
+    pid: u32,
+    container: Option<i64>,
+    exec: &Exec,
+    mut title_matches: F,
+) -> Result<Option<String>>
+where
+    F: FnMut(&Regex) -> Result<bool>,
+{
+    if let Some(ref title) = exec.wait_for.title {
+        if !title_matches(&Regex::new(title)?)? {
+            return Ok(Some(title.to_owned()));
+        }
+    }
+
+    let mut backend = backend(exec, pid, container)?;
+
+    thread::sleep(exec.wait_for.delay);
+
+    let commands = &exec.commands;
+    let between = exec.wait_for.between;
+    match exec.exec_type {
+        // Keys are input at once, unless there has to be a delay between them
+        ExecType::Keys if between == Duration::from_secs(0) => backend.keys(commands)?,
+        _ => {
+            for (index, command) in commands.iter().enumerate() {
+                if index > 0 {
+                    thread::sleep(between);
+                }
+
+                match exec.exec_type {
+                    ExecType::Text => {
+                        backend.text(command)?;
+                        backend.keys(&["Return".to_owned()])?;
+                    }
+                    ExecType::TextNoReturn => backend.text(command)?,
+                    ExecType::Keys => backend.keys(slice::from_ref(command))?,
+                    ExecType::Paste => {
+                        backend.paste(command, &exec.paste, exec.timeout)?;
+                        backend.keys(&["Return".to_owned()])?;
+                    }
+                }
+            }
+        }
+    }
+
+    // Only consecutive inputs are separated by `between`, sleeping or waiting for the title
+    // already does so.
+    let mut previous_input = !commands.is_empty();
+    for step in &exec.steps {
+        let input = !matches!(*step, ExecStep::Sleep(_) | ExecStep::WaitTitle(_));
+        if input && previous_input {
+            thread::sleep(between);
+        }
+        previous_input = input;
+
+        match *step {
+            ExecStep::Text(ref text) => backend.text(text)?,
+            ExecStep::Paste(ref text) => backend.paste(text, &exec.paste, exec.timeout)?,
+            ExecStep::Keys(ref keys) => backend.keys(keys)?,
+            ExecStep::Sleep(duration) => thread::sleep(duration),
+            ExecStep::WaitTitle(ref title) => {
+                if !title_matches(&Regex::new(title)?)? {
+                    return Ok(Some(title.to_owned()));
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Backend simulating key-presses through the XTEST extension of the X server.
+///
+/// The window is focused through i3 before every input, as the simulated key-presses are received
+/// by the focused window. If the focus changes while inputting, the input is aborted.
+struct X11 {
+    connection: x11::Connection,
+    window: Window,
+    container: Option<i64>,
+    timeout: Duration,
+}
+
+impl X11 {
+    /// Connect to the X server and wait for the application with the process id `pid` to map a
+    /// window, failing if it doesn't within `timeout`. `container` is the i3 container holding
+    /// the window, if it is known.
+    fn new(pid: u32, container: Option<i64>, timeout: Duration) -> Result<Self> {
+        let connection = x11::Connection::connect()?;
+
+        let start = Instant::now();
+        let window = loop {
+            if let Some(window) = connection.find_window(pid)? {
+                break window;
+            }
+            if start.elapsed() >= timeout {
+                return Err(ErrorKind::TextOrKeyInputFailed.into());
+            }
+            thread::sleep(Duration::from_millis(100));
+        };
+
+        Ok(X11 {
+            connection,
+            window,
+            container,
+            timeout,
+        })
+    }
+
+    /// Focus the window through i3, waiting for the X server to report it as focused.
+    fn focus(&mut self) -> Result<()> {
+        let mut i3 = I3Connection::connect()?;
+        let container = match self.container {
+            Some(container) => container,
+            None => {
+                let window = Some(self.window as i32);
+                let container = tree::nodes(&i3.get_tree()?)
+                    .into_iter()
+                    .find(|node| node.window == window)
+                    .map(|node| node.id)
+                    .ok_or(ErrorKind::TextOrKeyInputFailed)?;
+                self.container = Some(container);
+                container
+            }
+        };
+
+        let start = Instant::now();
+        loop {
+            i3.run_command(&format!("[con_id={}] focus", container))?;
+            if self.connection.focused_window()? == self.window {
+                return Ok(());
+            }
+            if start.elapsed() >= self.timeout {
+                return Err(ErrorKind::TextOrKeyInputFailed.into());
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    /// Press the keys producing `keysyms`, failing if the window lost the focus in the meantime,
+    /// such that no input ends up in another window.
+    fn chord(&mut self, keysyms: &[Keysym]) -> Result<()> {
+        if self.connection.focused_window()? != self.window {
+            return Err(ErrorKind::TextOrKeyInputFailed.into());
+        }
+
+        self.connection.chord(keysyms)
+    }
+}
+
+impl Backend for X11 {
+    fn text(&mut self, text: &str) -> Result<()> {
+        self.focus()?;
+        for c in text.chars() {
+            self.chord(&[x11::char_keysym(c)])?;
+        }
+
+        Ok(())
+    }
+
+    fn keys(&mut self, keys: &[String]) -> Result<()> {
+        self.focus()?;
+        for key in keys {
+            let keysyms = key
+                .split('+')
+                .map(x11::keysym)
+                .collect::<Result<Vec<_>>>()?;
+            self.chord(&keysyms)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the characters a terminal receives if the key, given in the format `xdotool key` expects,
+/// is pressed.
+///
+/// This is used by backends that send text to a terminal rather than key-presses to a window.
+///
+/// # Returns
+///
+/// A `Result` which is:
+///
+/// - `Ok`: the characters the terminal receives.
+/// - `Err`: [`ErrorKind::UnknownKey`][ErrorKind-UnknownKey] if the key or one of its modifiers
+///   isn't known, or the combination has no representation in a terminal (e.g. `ctrl+F1`).
+///
+///
+/// [ErrorKind-UnknownKey]: ../errors/enum.ErrorKind.html#variant.UnknownKey
+pub fn key_sequence(key: &str) -> Result<String> {
+    let unknown = || Error::from(ErrorKind::UnknownKey(key.to_owned()));
+
+    let mut names = key.split('+').collect::<Vec<_>>();
+    let name = names.pop().ok_or_else(unknown)?;
+    let modifiers = names;
+
+    let mut sequence = match name {
+        "Return" | "KP_Enter" => "\r".to_owned(),
+        "Tab" => "\t".to_owned(),
+        "Escape" => "\x1b".to_owned(),
+        "BackSpace" => "\x7f".to_owned(),
+        "Insert" => "\x1b[2~".to_owned(),
+        "Delete" => "\x1b[3~".to_owned(),
+        "Home" => "\x1b[H".to_owned(),
+        "End" => "\x1b[F".to_owned(),
+        "Up" => "\x1b[A".to_owned(),
+        "Down" => "\x1b[B".to_owned(),
+        "Right" => "\x1b[C".to_owned(),
+        "Left" => "\x1b[D".to_owned(),
+        "Prior" | "Page_Up" => "\x1b[5~".to_owned(),
+        "Next" | "Page_Down" => "\x1b[6~".to_owned(),
+        "F1" => "\x1bOP".to_owned(),
+        "F2" => "\x1bOQ".to_owned(),
+        "F3" => "\x1bOR".to_owned(),
+        "F4" => "\x1bOS".to_owned(),
+        "F5" => "\x1b[15~".to_owned(),
+        "F6" => "\x1b[17~".to_owned(),
+        "F7" => "\x1b[18~".to_owned(),
+        "F8" => "\x1b[19~".to_owned(),
+        "F9" => "\x1b[20~".to_owned(),
+        "F10" => "\x1b[21~".to_owned(),
+        "F11" => "\x1b[23~".to_owned(),
+        "F12" => "\x1b[24~".to_owned(),
+        _ => {
+            // Latin 1 keysyms correspond to their code point, Unicode keysyms are offset
+            let keysym = x11::keysym(name).map_err(|_| unknown())?;
+            let c = match keysym {
+                0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+                0x0100_0000..=0x0110_ffff => char::from_u32(keysym - 0x0100_0000),
+                _ => None,
+            };
+            c.ok_or_else(unknown)?.to_string()
+        }
+    };
+
+    for modifier in &modifiers {
+        if let "shift" | "Shift_L" | "Shift_R" = *modifier {
+            // Terminals receive a back-tab for shift+Tab
+            sequence = match sequence.as_str() {
+                "\t" => "\x1b[Z".to_owned(),
+                _ => sequence.to_uppercase(),
+            };
+        }
+    }
+    for modifier in &modifiers {
+        match *modifier {
+            "shift" | "Shift_L" | "Shift_R" => (),
+            "ctrl" | "control" | "Control_L" | "Control_R" => {
+                let mut chars = sequence.chars();
+                sequence = match (chars.next(), chars.next()) {
+                    (Some(' '), None) => "\0".to_owned(),
+                    (Some(c @ 'a'..='z'), None) | (Some(c @ '@'..='_'), None) => {
+                        char::from(c.to_ascii_uppercase() as u8 & 0x1f).to_string()
+                    }
+                    _ => return Err(unknown()),
+                };
+            }
+            "alt" | "meta" | "Alt_L" | "Alt_R" | "Meta_L" | "Meta_R" => {
+                sequence.insert(0, '\x1b');
+            }
+            _ => return Err(unknown()),
+        }
+    }
+
+    Ok(sequence)
+}
+
+/// Run `program` with `args`, writing `input` to its standard input, and wait for it to exit. If
+/// it doesn't exit within `timeout`, it is killed.
+fn run(
+    program: &str,
+    args: &[&str],
+    input: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<ExitStatus> {
+    run_with_stdout(program, args, input, Stdio::null(), timeout)
+}
+
+/// Like [`run`](fn.run.html), but capturing the standard output of `program`, which is returned
+/// if it exits successfully.
+fn output(program: &str, args: &[&str], timeout: Duration) -> Result<Option<Vec<u8>>> {
+    // The output is written to a file, such that `program` can't block on a full pipe while it
+    // is waited for.
+    let mut file = tempfile::tempfile()?;
+    let status = run_with_stdout(program, args, None, file.try_clone()?.into(), timeout)?;
+    if !status.success() {
+        return Ok(None);
+    }
+
+    let mut output = vec![];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut output)?;
+
+    Ok(Some(output))
+}
+
+fn run_with_stdout(
+    program: &str,
+    args: &[&str],
+    input: Option<&[u8]>,
+    stdout: Stdio,
+    timeout: Duration,
+) -> Result<ExitStatus> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(stdout)
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => ErrorKind::ProgramNotFound(program.to_owned()).into(),
+            _ => Error::from(e),
+        })?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input)?;
+    }
+
+    // Return of `wait_timeout` is `None` if the process didn't exit.
+    match child.wait_timeout(timeout)? {
+        Some(status) => Ok(status),
+        None => {
+            child.kill()?;
+            child.wait()?;
+            Err(ErrorKind::TextOrKeyInputFailed.into())
+        }
+    }
+}
+
+/// Like [`run`](fn.run.html), but failing if `program` exits unsuccessfully.
+fn run_successfully(
+    program: &str,
+    args: &[&str],
+    input: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<()> {
+    if run(program, args, input, timeout)?.success() {
+        Ok(())
+    } else {
+        Err(ErrorKind::TextOrKeyInputFailed.into())
+    }
+}
+
+/// Run `program` with `args` until it succeeds, failing if it doesn't within `timeout`.
+fn wait_until(program: &str, args: &[&str], timeout: Duration) -> Result<()> {
+    let start = Instant::now();
+    loop {
+        if run(program, args, None, timeout)?.success() {
+            return Ok(());
+        }
+        if start.elapsed() >= timeout {
+            return Err(ErrorKind::TextOrKeyInputFailed.into());
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Backend running `xdotool`, which sends the key-events directly to the window using
+/// `SendEvent`s.
+struct Xdotool {
+    pid: String,
+    timeout: Duration,
+}
+
+impl Xdotool {
+    fn new(pid: u32, timeout: Duration) -> Self {
+        Xdotool {
+            pid: pid.to_string(),
+            timeout,
+        }
+    }
+
+    /// Run `xdotool` with `args` on the window of the application, killing it if it doesn't exit
+    /// within the timeout.
+    fn run(&self, args: &[&str]) -> Result<()> {
+        let base_parameters = [
+            "search",
+            "--sync",
+            "--onlyvisible",
+            "--any",
+            "--pid",
+            &self.pid,
+            "ignorepattern",
+            "windowfocus",
+            "--sync",
+            "%1",
+        ];
+        run(
+            "xdotool",
+            &[&base_parameters, args].concat(),
+            None,
+            self.timeout,
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Backend for Xdotool {
+    fn text(&mut self, text: &str) -> Result<()> {
+        self.run(&["type", "--window", "%1", text])
+    }
+
+    fn keys(&mut self, keys: &[String]) -> Result<()> {
+        let args = ["key", "--window", "%1"]
+            .iter()
+            .copied()
+            .chain(keys.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        self.run(&args)
+    }
+}
+
+/// Backend sending the text to a tmux pane using `tmux send-keys`.
+struct Tmux {
+    target: String,
+    timeout: Duration,
+}
+
+impl Tmux {
+    /// Wait for the tmux session of `target` to be created, failing if it isn't within `timeout`.
+    fn new(target: &str, timeout: Duration) -> Result<Self> {
+        wait_until("tmux", &["has-session", "-t", target], timeout)?;
+
+        Ok(Tmux {
+            target: target.to_owned(),
+            timeout,
+        })
+    }
+}
+
+impl Backend for Tmux {
+    fn text(&mut self, text: &str) -> Result<()> {
+        run_successfully(
+            "tmux",
+            &["send-keys", "-t", &self.target, "-l", "--", text],
+            None,
+            self.timeout,
+        )
+    }
+
+    fn paste(&mut self, text: &str, _: &Paste, _: Duration) -> Result<()> {
+        // The buffer is deleted after pasting, `-p` uses bracketed paste if the application
+        // supports it.
+        run_successfully(
+            "tmux",
+            &["set-buffer", "-b", "i3nator", "--", text],
+            None,
+            self.timeout,
+        )?;
+        run_successfully(
+            "tmux",
+            &[
+                "paste-buffer",
+                "-b",
+                "i3nator",
+                "-d",
+                "-p",
+                "-t",
+                &self.target,
+            ],
+            None,
+            self.timeout,
+        )
+    }
+}
+
+/// Backend sending the text to a kitty instance using `kitty @ send-text`, i.e. kitty's remote
+/// control.
+struct Kitty {
+    target: String,
+    timeout: Duration,
+}
+
+impl Kitty {
+    /// Wait for the kitty instance listening on `target` to accept remote control commands,
+    /// failing if it doesn't within `timeout`.
+    fn new(target: &str, timeout: Duration) -> Result<Self> {
+        wait_until("kitty", &["@", "--to", target, "ls"], timeout)?;
+
+        Ok(Kitty {
+            target: target.to_owned(),
+            timeout,
+        })
+    }
+}
+
+impl Backend for Kitty {
+    fn text(&mut self, text: &str) -> Result<()> {
+        // Text passed as an argument is interpreted for escapes, text read from stdin is not.
+        run_successfully(
+            "kitty",
+            &["@", "--to", &self.target, "send-text", "--stdin"],
+            Some(text.as_bytes()),
+            self.timeout,
+        )
+    }
+
+    fn paste(&mut self, text: &str, _: &Paste, _: Duration) -> Result<()> {
+        run_successfully(
+            "kitty",
+            &[
+                "@",
+                "--to",
+                &self.target,
+                "send-text",
+                "--stdin",
+                "--bracketed-paste",
+                "auto",
+            ],
+            Some(text.as_bytes()),
+            self.timeout,
+        )
+    }
+}
+
+/// Backend sending the text to a wezterm pane using `wezterm cli send-text`.
+struct Wezterm {
+    pane_id: String,
+    timeout: Duration,
+}
+
+impl Wezterm {
+    /// Wait for wezterm to list the pane `target`, failing if it doesn't within `timeout`.
+    fn new(target: &str, timeout: Duration) -> Result<Self> {
+        let start = Instant::now();
+        loop {
+            if let Some(output) = output("wezterm", &["cli", "list", "--format", "json"], timeout)?
+            {
+                let panes: Vec<serde_json::Value> = serde_json::from_slice(&output)?;
+                if panes
+                    .iter()
+                    .filter_map(|pane| pane["pane_id"].as_u64())
+                    .any(|pane_id| pane_id.to_string() == target)
+                {
+                    break;
+                }
+            }
+            if start.elapsed() >= timeout {
+                return Err(ErrorKind::TextOrKeyInputFailed.into());
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        Ok(Wezterm {
+            pane_id: target.to_owned(),
+            timeout,
+        })
+    }
+}
+
+impl Backend for Wezterm {
+    fn text(&mut self, text: &str) -> Result<()> {
+        run_successfully(
+            "wezterm",
+            &[
+                "cli",
+                "send-text",
+                "--pane-id",
+                &self.pane_id,
+                "--no-paste",
+                "--",
+                text,
+            ],
+            None,
+            self.timeout,
+        )
+    }
+
+    fn paste(&mut self, text: &str, _: &Paste, _: Duration) -> Result<()> {
+        // Without `--no-paste`, the text is sent using bracketed paste if the application
+        // supports it.
+        run_successfully(
+            "wezterm",
+            &["cli", "send-text", "--pane-id", &self.pane_id, "--", text],
+            None,
+            self.timeout,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_sequence_keys() {
+        assert_eq!(key_sequence("Return").unwrap(), "\r");
+        assert_eq!(key_sequence("KP_Enter").unwrap(), "\r");
+        assert_eq!(key_sequence("Tab").unwrap(), "\t");
+        assert_eq!(key_sequence("Escape").unwrap(), "\x1b");
+        assert_eq!(key_sequence("BackSpace").unwrap(), "\x7f");
+        assert_eq!(key_sequence("Up").unwrap(), "\x1b[A");
+        assert_eq!(key_sequence("Page_Down").unwrap(), "\x1b[6~");
+        assert_eq!(key_sequence("F1").unwrap(), "\x1bOP");
+        assert_eq!(key_sequence("F12").unwrap(), "\x1b[24~");
+    }
+
+    #[test]
+    fn key_sequence_characters() {
+        assert_eq!(key_sequence("a").unwrap(), "a");
+        assert_eq!(key_sequence("space").unwrap(), " ");
+        assert_eq!(key_sequence("slash").unwrap(), "/");
+        assert_eq!(key_sequence("0xe4").unwrap(), "ä");
+        assert_eq!(key_sequence("0x10020ac").unwrap(), "€");
+    }
+
+    #[test]
+    fn key_sequence_modifiers() {
+        assert_eq!(key_sequence("shift+a").unwrap(), "A");
+        assert_eq!(key_sequence("shift+Tab").unwrap(), "\x1b[Z");
+        assert_eq!(key_sequence("Shift_L+Tab").unwrap(), "\x1b[Z");
+        assert_eq!(key_sequence("ctrl+c").unwrap(), "\x03");
+        assert_eq!(key_sequence("ctrl+shift+c").unwrap(), "\x03");
+        assert_eq!(key_sequence("control+bracketleft").unwrap(), "\x1b");
+        assert_eq!(key_sequence("ctrl+space").unwrap(), "\0");
+        assert_eq!(key_sequence("alt+b").unwrap(), "\x1bb");
+        assert_eq!(key_sequence("ctrl+alt+x").unwrap(), "\x1b\x18");
+    }
+
+    #[test]
+    fn key_sequence_unknown() {
+        for key in &["Retrun", "hyper+a", "ctrl+F1", "ctrl+Up", "0xff13", "ctrl+"] {
+            match key_sequence(key) {
+                Err(Error(ErrorKind::UnknownKey(k), _)) => assert_eq!(k, *key),
+                other => panic!("expected UnknownKey for '{}', got {:?}", key, other),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct MockSelection {
+        contents: Option<Vec<u8>>,
+        ignored: bool,
+        written: Vec<Vec<u8>>,
+        restored: Vec<Vec<u8>>,
+        cleared: bool,
+    }
+
+    impl Selection for MockSelection {
+        fn read(&mut self) -> Result<Option<Vec<u8>>> {
+            Ok(self.contents.to_owned())
+        }
+
+        fn write(&mut self, contents: &[u8]) -> Result<()> {
+            self.contents = Some(contents.to_owned());
+            self.written.push(contents.to_owned());
+            Ok(())
+        }
+
+        fn wait_for_request(&mut self) -> Result<bool> {
+            Ok(!self.ignored)
+        }
+
+        fn restore(&mut self, contents: &[u8]) -> Result<()> {
+            self.contents = Some(contents.to_owned());
+            self.restored.push(contents.to_owned());
+            Ok(())
+        }
+
+        fn clear(&mut self) -> Result<()> {
+            self.contents = None;
+            self.cleared = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn paste_through_restores() {
+        let mut selection = MockSelection {
+            contents: Some(b"previous".to_vec()),
+            ..Default::default()
+        };
+        let mut pressed = false;
+        paste_through(&mut selection, "echo hi", || {
+            pressed = true;
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(pressed);
+        assert_eq!(selection.contents, Some(b"previous".to_vec()));
+        assert_eq!(selection.written, vec![b"echo hi".to_vec()]);
+        assert_eq!(selection.restored, vec![b"previous".to_vec()]);
+        assert!(!selection.cleared);
+    }
+
+    #[test]
+    fn paste_through_clears() {
+        let mut selection = MockSelection::default();
+        paste_through(&mut selection, "echo hi", || Ok(())).unwrap();
+
+        assert_eq!(selection.contents, None);
+        assert_eq!(selection.written, vec![b"echo hi".to_vec()]);
+        assert!(selection.restored.is_empty());
+        assert!(selection.cleared);
+    }
+
+    #[test]
+    fn paste_through_restores_on_failure() {
+        let failing_keys = || Err(ErrorKind::TextOrKeyInputFailed.into());
+
+        let mut selection = MockSelection {
+            contents: Some(b"previous".to_vec()),
+            ..Default::default()
+        };
+        match paste_through(&mut selection, "echo hi", failing_keys) {
+            Err(Error(ErrorKind::TextOrKeyInputFailed, _)) => (),
+            result => panic!("expected the key-presses to fail, got {:?}", result),
+        }
+        assert_eq!(selection.contents, Some(b"previous".to_vec()));
+
+        let mut selection = MockSelection::default();
+        assert!(paste_through(&mut selection, "echo hi", failing_keys).is_err());
+        assert_eq!(selection.contents, None);
+        assert!(selection.cleared);
+    }
+
+    #[test]
+    fn paste_through_not_requested() {
+        let mut selection = MockSelection {
+            contents: Some(b"previous".to_vec()),
+            ignored: true,
+            ..Default::default()
+        };
+        match paste_through(&mut selection, "echo hi", || Ok(())) {
+            Err(Error(ErrorKind::PasteNotRequested, _)) => (),
+            result => panic!("expected the paste not to be requested, got {:?}", result),
+        }
+        assert_eq!(selection.contents, Some(b"previous".to_vec()));
+        assert_eq!(selection.restored, vec![b"previous".to_vec()]);
+    }
+}
@@ -0,0 +1,185 @@
+// Copyright Pit Kleyersburg <pitkley@googlemail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified or distributed
+// except according to those terms.
+
+//! Module for fragment handling.
+//!
+//! Fragments contain applications which can be included in multiple projects using `[[include]]`
+//! sections.
+
+use crate::{
+    configfiles::{self, ConfigFile, ConfigFileImpl},
+    errors::*,
+    types::FragmentConfig,
+    variables,
+};
+use lazy_static::lazy_static;
+use std::{
+    collections::BTreeMap,
+    ffi::{OsStr, OsString},
+    fs::File,
+    io::{prelude::*, BufReader},
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+lazy_static! {
+    static ref FRAGMENTS_PREFIX: OsString = OsString::from("fragments");
+}
+
+/// A structure representing a managed fragment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fragment {
+    configfile: ConfigFileImpl,
+
+    /// The name of the fragment.
+    ///
+    /// As represented by the stem of the filename on disk.
+    pub name: String,
+
+    /// The path to the fragment configuration.
+    pub path: PathBuf,
+}
+
+impl Deref for Fragment {
+    type Target = ConfigFileImpl;
+
+    fn deref(&self) -> &ConfigFileImpl {
+        &self.configfile
+    }
+}
+
+impl Fragment {
+    fn from_configfile(configfile: ConfigFileImpl) -> Self {
+        let name = configfile.name.to_owned();
+        let path = configfile.path.clone();
+
+        Fragment {
+            configfile,
+            name,
+            path,
+        }
+    }
+
+    /// Loads the fragment's configuration.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is:
+    ///
+    /// - `Ok`: an instance of [`FragmentConfig`][struct-FragmentConfig] for the fragment.
+    /// - `Err`: an error, e.g. if parsing the configuration failed.
+    ///
+    ///
+    /// [struct-FragmentConfig]: ../types/struct.FragmentConfig.html
+    pub fn config(&self) -> Result<FragmentConfig> {
+        load(&self.path, &BTreeMap::new())
+    }
+}
+
+impl ConfigFile for Fragment {
+    fn create<S: AsRef<OsStr> + ?Sized>(name: &S) -> Result<Self> {
+        let configfile = ConfigFileImpl::create(FRAGMENTS_PREFIX.as_os_str(), name.as_ref())?;
+        Ok(Fragment::from_configfile(configfile))
+    }
+
+    fn create_from_template<S: AsRef<OsStr> + ?Sized>(name: &S, template: &[u8]) -> Result<Self> {
+        let configfile = ConfigFileImpl::create_from_template(
+            FRAGMENTS_PREFIX.as_os_str(),
+            name.as_ref(),
+            template,
+        )?;
+        Ok(Fragment::from_configfile(configfile))
+    }
+
+    fn from_path<P: AsRef<Path> + ?Sized>(path: &P) -> Result<Self> {
+        let configfile = ConfigFileImpl::from_path(path)?;
+        Ok(Fragment::from_configfile(configfile))
+    }
+
+    fn open<S: AsRef<OsStr> + ?Sized>(name: &S) -> Result<Self> {
+        let configfile = ConfigFileImpl::open(FRAGMENTS_PREFIX.as_os_str(), name.as_ref())?;
+        Ok(Fragment::from_configfile(configfile))
+    }
+
+    fn copy<S: AsRef<OsStr> + ?Sized>(&self, new_name: &S) -> Result<Self> {
+        let configfile = self.configfile.copy(new_name)?;
+        Ok(Fragment::from_configfile(configfile))
+    }
+
+    fn delete(&self) -> Result<()> {
+        self.configfile.delete()?;
+        Ok(())
+    }
+
+    fn rename<S: AsRef<OsStr> + ?Sized>(&self, new_name: &S) -> Result<Self> {
+        let configfile = self.configfile.rename(new_name)?;
+        Ok(Fragment::from_configfile(configfile))
+    }
+
+    fn verify(&self) -> Result<()> {
+        let config = self.config()?;
+
+        // Verify that all working directories exist
+        for application in &config.applications {
+            if let Some(ref path) = application.working_directory {
+                if !path.exists() {
+                    return Err(
+                        ErrorKind::PathDoesntExist(path.to_string_lossy().into_owned()).into(),
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn list() -> Vec<OsString> {
+        configfiles::list(&*FRAGMENTS_PREFIX)
+    }
+
+    fn name(&self) -> String {
+        self.name.to_owned()
+    }
+
+    fn path(&self) -> PathBuf {
+        self.path.to_owned()
+    }
+
+    fn prefix() -> &'static OsStr {
+        &*FRAGMENTS_PREFIX
+    }
+}
+
+/// Get a list of all fragment names.
+///
+/// This will check the current users XDG base directories for `i3nator` fragment configurations,
+/// and return a list of their names for use with e.g. [`Fragment::open`][fn-Fragment-open].
+///
+/// [fn-Fragment-open]: struct.Fragment.html#method.open
+pub fn list() -> Vec<OsString> {
+    configfiles::list(&*FRAGMENTS_PREFIX)
+}
+
+/// Load the fragment configuration stored at `path`, interpolating the given variable `values`.
+pub(crate) fn load(path: &Path, values: &BTreeMap<String, String>) -> Result<FragmentConfig> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    // Deserializing from a `toml::Value` loses the position of errors, which is why it is only
+    // done if there are variables to interpolate.
+    if values.is_empty() {
+        return toml::from_str::<FragmentConfig>(&contents).map_err(|e| e.into());
+    }
+
+    let mut value = toml::from_str::<toml::Value>(&contents)?;
+    variables::interpolate(&mut value, values);
+    let mut config = value.try_into::<FragmentConfig>()?;
+    variables::interpolate_commands(&mut config.applications, values);
+    Ok(config)
+}
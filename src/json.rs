@@ -0,0 +1,325 @@
+// Copyright Pit Kleyersburg <pitkley@googlemail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified or distributed
+// except according to those terms.
+
+use crate::errors::*;
+use std::{iter::Peekable, str::CharIndices};
+
+// Minimal JSON parser for the format i3 accepts for `append_layout`, i.e. multiple concatenated
+// JSON values which can contain `//` and `/* */` comments. Other than `serde_json` it keeps track
+// of where every value is located, such that errors can point to the offending line and column.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    pub kind: Kind,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self.kind {
+            Kind::Object(ref members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.kind {
+            Kind::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self.kind {
+            Kind::Array(ref values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn error<S: Into<String>>(&self, message: S) -> Error {
+        ErrorKind::InvalidLayout(self.line, self.column, message.into()).into()
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser {
+            chars: input.char_indices().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn error<S: Into<String>>(&self, message: S) -> Error {
+        ErrorKind::InvalidLayout(self.line, self.column, message.into()).into()
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, ch)| ch)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let (_, ch) = self.chars.next()?;
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        match self.peek() {
+            Some(ch) if ch == expected => {
+                self.next();
+                Ok(())
+            }
+            Some(ch) => Err(self.error(format!("expected '{}', found '{}'", expected, ch))),
+            None => Err(self.error(format!("expected '{}', found end of input", expected))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> Result<()> {
+        loop {
+            match self.peek() {
+                Some(ch) if ch.is_whitespace() => {
+                    self.next();
+                }
+                Some('/') => {
+                    let (line, column) = (self.line, self.column);
+                    self.next();
+                    match self.next() {
+                        Some('/') => while !matches!(self.next(), Some('\n') | None) {},
+                        Some('*') => loop {
+                            match self.next() {
+                                Some('*') if self.peek() == Some('/') => {
+                                    self.next();
+                                    break;
+                                }
+                                Some(_) => (),
+                                None => {
+                                    return Err(ErrorKind::InvalidLayout(
+                                        line,
+                                        column,
+                                        "unterminated comment".to_owned(),
+                                    )
+                                    .into())
+                                }
+                            }
+                        },
+                        _ => {
+                            return Err(ErrorKind::InvalidLayout(
+                                line,
+                                column,
+                                "expected a comment".to_owned(),
+                            )
+                            .into())
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value> {
+        self.skip_whitespace()?;
+        let (line, column) = (self.line, self.column);
+        let kind = match self.peek() {
+            Some('{') => self.parse_object()?,
+            Some('[') => self.parse_array()?,
+            Some('"') => Kind::String(self.parse_string()?),
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => self.parse_number()?,
+            Some(ch) if ch.is_ascii_alphabetic() => match self.parse_word().as_str() {
+                "null" => Kind::Null,
+                "true" => Kind::Bool(true),
+                "false" => Kind::Bool(false),
+                word => {
+                    return Err(ErrorKind::InvalidLayout(
+                        line,
+                        column,
+                        format!("unexpected '{}'", word),
+                    )
+                    .into())
+                }
+            },
+            Some(ch) => return Err(self.error(format!("expected a value, found '{}'", ch))),
+            None => return Err(self.error("expected a value, found end of input")),
+        };
+
+        Ok(Value { kind, line, column })
+    }
+
+    fn parse_object(&mut self) -> Result<Kind> {
+        self.expect('{')?;
+        let mut members = vec![];
+        self.skip_whitespace()?;
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Kind::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string as object key"));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace()?;
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            members.push((key, value));
+
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(',') => self.next(),
+                Some('}') => {
+                    self.next();
+                    return Ok(Kind::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}' after object member")),
+            };
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Kind> {
+        self.expect('[')?;
+        let mut values = vec![];
+        self.skip_whitespace()?;
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(Kind::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(',') => self.next(),
+                Some(']') => {
+                    self.next();
+                    return Ok(Kind::Array(values));
+                }
+                _ => return Err(self.error("expected ',' or ']' after array element")),
+            };
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.next() {
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some('/') => string.push('/'),
+                    Some('b') => string.push('\u{8}'),
+                    Some('f') => string.push('\u{c}'),
+                    Some('n') => string.push('\n'),
+                    Some('r') => string.push('\r'),
+                    Some('t') => string.push('\t'),
+                    Some('u') => {
+                        let mut code = self.parse_hex()?;
+                        if (0xD800..0xDC00).contains(&code) {
+                            // Surrogate pair
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            let low = self.parse_hex()?;
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00));
+                        }
+                        string.push(
+                            ::std::char::from_u32(code)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?,
+                        );
+                    }
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(ch) => string.push(ch),
+            }
+        }
+    }
+
+    fn parse_hex(&mut self) -> Result<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next()
+                .and_then(|ch| ch.to_digit(16))
+                .ok_or_else(|| self.error("invalid unicode escape"))?;
+            code = code * 16 + digit;
+        }
+
+        Ok(code)
+    }
+
+    fn parse_number(&mut self) -> Result<Kind> {
+        let mut number = String::new();
+        while let Some(ch) = self.peek() {
+            if ch.is_ascii_digit() || "+-.eE".contains(ch) {
+                number.push(ch);
+                self.next();
+            } else {
+                break;
+            }
+        }
+
+        number
+            .parse()
+            .map(Kind::Number)
+            .map_err(|_| self.error(format!("invalid number '{}'", number)))
+    }
+
+    fn parse_word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(ch) = self.peek() {
+            if ch.is_ascii_alphanumeric() {
+                word.push(ch);
+                self.next();
+            } else {
+                break;
+            }
+        }
+
+        word
+    }
+}
+
+/// Parse all JSON values contained in `input`.
+pub fn parse(input: &str) -> Result<Vec<Value>> {
+    let mut parser = Parser::new(input);
+    let mut values = vec![];
+    loop {
+        parser.skip_whitespace()?;
+        if parser.peek().is_none() {
+            return Ok(values);
+        }
+        values.push(parser.parse_value()?);
+    }
+}
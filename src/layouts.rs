@@ -11,10 +11,18 @@
 use crate::{
     configfiles::{self, ConfigFile, ConfigFileImpl},
     errors::*,
+    json, tree,
+};
+use i3ipc::{
+    reply::{Node, NodeBorder, NodeLayout, NodeType, WindowProperty},
+    I3Connection,
 };
 use lazy_static::lazy_static;
+use serde_json::{json, Map, Value};
 use std::{
     ffi::{OsStr, OsString},
+    fs::File,
+    io::{prelude::*, BufReader},
     ops::Deref,
     path::{Path, PathBuf},
 };
@@ -99,7 +107,11 @@ impl ConfigFile for Layout {
     }
 
     fn verify(&self) -> Result<()> {
-        Ok(())
+        let mut file = BufReader::new(File::open(&self.path)?);
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        verify(&contents)
     }
 
     fn list() -> Vec<OsString> {
@@ -128,3 +140,328 @@ impl ConfigFile for Layout {
 pub fn list() -> Vec<OsString> {
     configfiles::list(&*LAYOUTS_PREFIX)
 }
+
+/// Verify that the given layout `contents` can be appended by i3.
+///
+/// i3 accepts multiple concatenated JSON objects which can contain `//` and `/* */` comments, as
+/// produced by `i3-save-tree`. Every object has to describe a container with a valid `type`,
+/// `layout` and `border`, its `swallows` criteria have to be a list of objects and the `percent`
+/// values of sibling containers must not exceed a sum of one.
+///
+/// # Returns
+///
+/// A `Result` which is:
+///
+/// - `Ok`: if the layout is valid.
+/// - `Err`: an [`ErrorKind::InvalidLayout`][errorkind-InvalidLayout], pointing to the line and
+///   column of the offending JSON value.
+///
+/// [errorkind-InvalidLayout]: ../errors/enum.ErrorKind.html#variant.InvalidLayout
+pub fn verify(contents: &str) -> Result<()> {
+    parse(contents).map(|_| ())
+}
+
+pub(crate) fn parse(contents: &str) -> Result<Vec<json::Value>> {
+    let containers = json::parse(contents)?;
+    if containers.is_empty() {
+        return Err(ErrorKind::InvalidLayout(
+            1,
+            1,
+            "layout does not contain any containers".to_owned(),
+        )
+        .into());
+    }
+
+    for container in &containers {
+        verify_container(container)?;
+    }
+    verify_percentages(&containers)?;
+
+    Ok(containers)
+}
+
+/// A container of a layout that swallows windows, i.e. a placeholder for an application window.
+pub(crate) struct Placeholder {
+    /// The line of the layout the placeholder is defined on.
+    pub line: usize,
+
+    /// The sets of criteria of the placeholder, any of which has to match for a window to be
+    /// swallowed.
+    pub criteria: Vec<Vec<(String, String)>>,
+}
+
+/// Get all placeholders contained in the given, verified layout containers.
+pub(crate) fn placeholders(containers: &[json::Value]) -> Vec<Placeholder> {
+    let mut placeholders = vec![];
+    for container in containers {
+        if let Some(swallows) = container.get("swallows").and_then(json::Value::as_array) {
+            placeholders.push(Placeholder {
+                line: container.line,
+                criteria: swallows
+                    .iter()
+                    .map(|criteria| match criteria.kind {
+                        json::Kind::Object(ref criteria) => criteria
+                            .iter()
+                            .filter_map(|(key, value)| {
+                                value
+                                    .as_str()
+                                    .map(|value| (key.to_owned(), value.to_owned()))
+                            })
+                            .collect(),
+                        _ => vec![],
+                    })
+                    .collect(),
+            });
+        }
+        for key in &["nodes", "floating_nodes"] {
+            if let Some(nodes) = container.get(key).and_then(json::Value::as_array) {
+                placeholders.extend(self::placeholders(nodes));
+            }
+        }
+    }
+
+    placeholders
+}
+
+fn verify_container(container: &json::Value) -> Result<()> {
+    let members = match container.kind {
+        json::Kind::Object(ref members) => members,
+        _ => return Err(container.error("expected a container object")),
+    };
+
+    for (key, value) in members {
+        match key.as_str() {
+            "type" => verify_one_of(
+                key,
+                value,
+                &["con", "floating_con", "workspace", "output", "root"],
+            )?,
+            "layout" => verify_one_of(
+                key,
+                value,
+                &[
+                    "default", "splith", "splitv", "stacked", "stacking", "tabbed", "dockarea",
+                    "output",
+                ],
+            )?,
+            "border" => verify_one_of(key, value, &["normal", "none", "pixel", "1pixel"])?,
+            "percent" => match value.kind {
+                json::Kind::Null => (),
+                json::Kind::Number(percent) if percent > 0.0 && percent <= 1.0 => (),
+                _ => return Err(value.error("expected `percent` to be a number between 0 and 1")),
+            },
+            "nodes" | "floating_nodes" => {
+                let nodes = value
+                    .as_array()
+                    .ok_or_else(|| value.error(format!("expected `{}` to be a list", key)))?;
+                for node in nodes {
+                    verify_container(node)?;
+                }
+                if key == "nodes" {
+                    verify_percentages(nodes)?;
+                }
+            }
+            "swallows" => {
+                let swallows = value
+                    .as_array()
+                    .ok_or_else(|| value.error("expected `swallows` to be a list"))?;
+                for criteria in swallows {
+                    let criteria = match criteria.kind {
+                        json::Kind::Object(ref criteria) => criteria,
+                        _ => return Err(criteria.error("expected swallow criteria object")),
+                    };
+                    for (_, value) in criteria {
+                        match value.kind {
+                            json::Kind::String(_) | json::Kind::Number(_) => (),
+                            _ => {
+                                return Err(value
+                                    .error("expected swallow criteria to be a string or a number"))
+                            }
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+fn verify_one_of(key: &str, value: &json::Value, allowed: &[&str]) -> Result<()> {
+    match value.as_str() {
+        Some(s) if allowed.contains(&s) => Ok(()),
+        _ => Err(value.error(format!(
+            "expected `{}` to be one of: {}",
+            key,
+            allowed.join(", ")
+        ))),
+    }
+}
+
+fn verify_percentages(siblings: &[json::Value]) -> Result<()> {
+    // i3-save-tree rounds percentages, so allow for a small margin
+    let sum: f64 = siblings
+        .iter()
+        .filter(|sibling| sibling.get("type").and_then(json::Value::as_str) != Some("floating_con"))
+        .filter_map(|sibling| match sibling.get("percent")?.kind {
+            json::Kind::Number(percent) => Some(percent),
+            _ => None,
+        })
+        .sum();
+    if sum > 1.01 {
+        let first = &siblings[0];
+        return Err(first.error(format!(
+            "the `percent` values of sibling containers sum up to {}, exceeding 1",
+            sum
+        )));
+    }
+
+    Ok(())
+}
+
+/// Capture the layout of a workspace in the format expected by i3's `append_layout`.
+///
+/// This is comparable to what `i3-save-tree` produces, with the difference that the swallow
+/// criteria of every window are filled in from the actual window properties (`class`,
+/// `instance`, `title` and `window_role`), rather than being commented out.
+///
+/// # Parameters
+///
+/// - `i3`: An `I3Connection` used to retrieve the current layout tree.
+/// - `workspace`: The name of the workspace to capture. If `None`, the currently focused
+///   workspace is used.
+///
+/// # Returns
+///
+/// A `Result` which is:
+///
+/// - `Ok`: the layout of the workspace, which can be stored using
+///   [`Layout::create_from_template`][fn-Layout-create_from_template].
+/// - `Err`: an error, e.g. if the workspace does not exist.
+///
+///
+/// [fn-Layout-create_from_template]: struct.Layout.html#method.create_from_template
+pub fn capture_workspace(i3: &mut I3Connection, workspace: Option<&str>) -> Result<String> {
+    let workspace = match workspace {
+        Some(workspace) => workspace.to_owned(),
+        None => tree::focused_workspace(i3)?,
+    };
+
+    let root = i3.get_tree()?;
+    let node = tree::find_workspace(&root, &workspace)
+        .ok_or_else(|| ErrorKind::UnknownWorkspace(workspace.to_owned()))?;
+
+    workspace_layout(node)
+}
+
+/// Convert the tree of a workspace into the format expected by i3's `append_layout`, as described
+/// in [`capture_workspace`](fn.capture_workspace.html).
+///
+/// Containers that don't hold a window, directly or through their children, are left out, as
+/// they can't swallow anything when the layout is restored.
+///
+/// # Parameters
+///
+/// - `workspace`: The node of the workspace, e.g. as retrieved using `I3Connection::get_tree`.
+///
+/// # Returns
+///
+/// A `Result` which is:
+///
+/// - `Ok`: the layout of the workspace.
+/// - `Err`: an error, if the layout couldn't be serialized.
+pub fn workspace_layout(workspace: &Node) -> Result<String> {
+    let containers = workspace
+        .nodes
+        .iter()
+        .chain(workspace.floating_nodes.iter())
+        .filter_map(node_to_layout)
+        .map(|layout| serde_json::to_string_pretty(&layout))
+        .collect::<::std::result::Result<Vec<_>, _>>()?;
+
+    Ok(containers.join("\n\n"))
+}
+
+fn node_to_layout(node: &Node) -> Option<Value> {
+    let mut layout = Map::new();
+
+    layout.insert(
+        "type".to_owned(),
+        json!(if node.nodetype == NodeType::FloatingCon {
+            "floating_con"
+        } else {
+            "con"
+        }),
+    );
+    if let Some(border) = match node.border {
+        NodeBorder::Normal => Some("normal"),
+        NodeBorder::None => Some("none"),
+        NodeBorder::Pixel => Some("pixel"),
+        NodeBorder::Unknown => None,
+    } {
+        layout.insert("border".to_owned(), json!(border));
+    }
+    layout.insert(
+        "current_border_width".to_owned(),
+        json!(node.current_border_width),
+    );
+    if let Some(percent) = node.percent {
+        layout.insert("percent".to_owned(), json!(percent));
+    }
+    if node.nodetype == NodeType::FloatingCon {
+        let (x, y, width, height) = node.rect;
+        layout.insert(
+            "rect".to_owned(),
+            json!({ "x": x, "y": y, "width": width, "height": height }),
+        );
+    }
+
+    if node.nodes.is_empty() && node.floating_nodes.is_empty() {
+        // Leaf containers hold the windows, which have to be swallowed when the layout is
+        // restored.
+        node.window?;
+        if let Some(ref name) = node.name {
+            layout.insert("name".to_owned(), json!(name));
+        }
+        if let Some(ref properties) = node.window_properties {
+            let swallows = properties
+                .iter()
+                .filter_map(|(property, value)| {
+                    let key = match *property {
+                        WindowProperty::Class => "class",
+                        WindowProperty::Instance => "instance",
+                        WindowProperty::Title => "title",
+                        WindowProperty::WindowRole => "window_role",
+                        WindowProperty::TransientFor => return None,
+                    };
+                    Some((key.to_owned(), json!(format!("^{}$", regex::escape(value)))))
+                })
+                .collect::<Map<_, _>>();
+            layout.insert("swallows".to_owned(), json!([swallows]));
+        }
+    } else {
+        let nodes = node
+            .nodes
+            .iter()
+            .chain(node.floating_nodes.iter())
+            .filter_map(node_to_layout)
+            .collect::<Vec<_>>();
+        if nodes.is_empty() {
+            return None;
+        }
+
+        if let Some(layout_name) = match node.layout {
+            NodeLayout::SplitH => Some("splith"),
+            NodeLayout::SplitV => Some("splitv"),
+            NodeLayout::Stacked => Some("stacked"),
+            NodeLayout::Tabbed => Some("tabbed"),
+            _ => None,
+        } {
+            layout.insert("layout".to_owned(), json!(layout_name));
+        }
+        layout.insert("nodes".to_owned(), Value::Array(nodes));
+    }
+
+    Some(Value::Object(layout))
+}
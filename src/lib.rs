@@ -31,8 +31,16 @@
 #![deny(missing_docs)]
 
 pub mod configfiles;
+mod dotenv;
 pub mod errors;
+mod exec;
+pub mod fragments;
+mod json;
 pub mod layouts;
 pub mod projects;
 mod shlex;
+pub mod state;
+mod tree;
 pub mod types;
+mod variables;
+mod x11;
@@ -40,7 +40,13 @@ use clap::Parser;
 use error_chain::quick_main;
 use getch::Getch;
 use i3ipc::I3Connection;
-use i3nator::{configfiles::ConfigFile, layouts::Layout, projects::Project};
+use i3nator::{
+    configfiles::ConfigFile,
+    fragments::Fragment,
+    layouts::{self, Layout},
+    projects::{self, Project, Selection},
+    state::ProjectState,
+};
 use lazy_static::lazy_static;
 use std::{
     convert::Into,
@@ -49,9 +55,11 @@ use std::{
     fs::File,
     io::{stdin, BufReader, Read},
     process::{Command, ExitStatus},
+    time::Duration,
 };
 
 static PROJECT_TEMPLATE: &[u8] = include_bytes!("../resources/project_template.toml");
+static FRAGMENT_TEMPLATE: &[u8] = include_bytes!("../resources/fragment_template.toml");
 
 lazy_static! {
     static ref GETCH: Getch = Getch::new();
@@ -174,16 +182,49 @@ fn command_rename<C: ConfigFile>(
     Ok(())
 }
 
+fn project_info(project_name: &OsStr) -> Result<()> {
+    command_info::<Project>(project_name)?;
+
+    // Show the order the applications are started in, along with their dependencies
+    let mut project = Project::open(project_name)?;
+    if let Ok(applications) = project.start_order() {
+        if !applications.is_empty() {
+            println!("Applications (in start order):");
+        }
+        for (index, application) in applications.iter().enumerate() {
+            if application.depends_on.is_empty() {
+                println!("  {}. {}", index + 1, application.name());
+            } else {
+                println!(
+                    "  {}. {} (depends on {})",
+                    index + 1,
+                    application.name(),
+                    application.depends_on.join(", ")
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn project_local(
     project_path: &OsStr,
     working_directory: Option<&OsStr>,
     workspace: Option<&str>,
+    variables: &[(String, String)],
+    selection: Selection,
 ) -> Result<()> {
     let mut project = Project::from_path(project_path)?;
+    for (name, value) in variables {
+        project.set_variable(name, value);
+    }
     let mut i3 = I3Connection::connect()?;
 
     println!("Starting project '{}'", project.name);
-    project.start(&mut i3, working_directory, workspace)?;
+    for warning in project.start(&mut i3, working_directory, workspace, selection)? {
+        println!("Warning: {}", warning);
+    }
 
     Ok(())
 }
@@ -203,16 +244,115 @@ fn project_new(project_name: &OsStr, no_edit: bool, no_verify: bool) -> Result<(
     Ok(())
 }
 
+fn project_snapshot(project_name: &OsStr, workspace: Option<&str>) -> Result<()> {
+    let mut i3 = I3Connection::connect()?;
+
+    let contents = projects::capture_workspace(&mut i3, workspace)?;
+    let project = Project::create_from_template(project_name, contents.as_bytes())?;
+    println!("Created project '{}'", project.name);
+
+    Ok(())
+}
+
 fn project_start(
     project_name: &OsStr,
     working_directory: Option<&OsStr>,
     workspace: Option<&str>,
+    variables: &[(String, String)],
+    selection: Selection,
+    force: bool,
+    restart: Option<Duration>,
 ) -> Result<()> {
     let mut project = Project::open(project_name)?;
+    for (name, value) in variables {
+        project.set_variable(name, value);
+    }
     let mut i3 = I3Connection::connect()?;
 
+    if project.is_running(&mut i3)? {
+        if let Some(timeout) = restart {
+            println!("Stopping running project '{}'", project.name);
+            project.stop(&mut i3, timeout)?;
+        } else if !force {
+            println!("Project '{}' is already running, focusing it", project.name);
+            project.focus(&mut i3)?;
+            return Ok(());
+        }
+    }
+
     println!("Starting project '{}'", project.name);
-    project.start(&mut i3, working_directory, workspace)?;
+    for warning in project.start(&mut i3, working_directory, workspace, selection)? {
+        println!("Warning: {}", warning);
+    }
+
+    Ok(())
+}
+
+fn project_status(project_name: Option<&OsStr>) -> Result<()> {
+    let states = if let Some(project_name) = project_name {
+        let project = Project::open(project_name)?;
+        match ProjectState::load(&project.name)? {
+            Some(state) => vec![state],
+            None => {
+                println!("Project '{}' is not running", project.name);
+                return Ok(());
+            }
+        }
+    } else {
+        let mut states = vec![];
+        for (name, state) in ProjectState::list() {
+            match state {
+                Ok(state) => states.push(state),
+                Err(error) => println!(
+                    "Warning: the state of project '{}' couldn't be read: {}",
+                    name, error
+                ),
+            }
+        }
+        states
+    };
+
+    if states.is_empty() {
+        println!("No projects are running");
+    }
+
+    for state in states {
+        if state.is_running() {
+            println!(
+                "Project '{}' is running on workspace(s) '{}' (started {} ago)",
+                state.name,
+                state.workspaces.join("', '"),
+                format_duration(state.uptime())
+            );
+        } else {
+            println!(
+                "Project '{}' is not running anymore, all applications exited",
+                state.name
+            );
+        }
+        for application in &state.applications {
+            println!(
+                "  {:<7} {} (pid {})",
+                if application.is_alive() {
+                    "alive:"
+                } else {
+                    "exited:"
+                },
+                application.program,
+                application.pid
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn project_stop(project_name: &OsStr, timeout: u64) -> Result<()> {
+    let project = Project::open(project_name)?;
+    let mut i3 = I3Connection::connect()?;
+
+    println!("Stopping project '{}'", project.name);
+    project.stop(&mut i3, Duration::from_secs(timeout))?;
 
     Ok(())
 }
@@ -228,7 +368,8 @@ fn project_verify<S: AsRef<OsStr>>(configfiles: &[S]) -> Result<()> {
     }
 
     for configfile_name in configfiles {
-        if let Err(e) = Project::open(&configfile_name)?.verify() {
+        let project = Project::open(&configfile_name)?;
+        if let Err(e) = project.verify() {
             println!(
                 "Configuration INVALID: '{}'",
                 configfile_name.to_string_lossy()
@@ -241,13 +382,21 @@ fn project_verify<S: AsRef<OsStr>>(configfiles: &[S]) -> Result<()> {
                 "Configuration   VALID: '{}'",
                 configfile_name.to_string_lossy()
             );
+            for warning in project.warnings() {
+                println!("    Warning: {}", warning);
+            }
         }
     }
 
     Ok(())
 }
 
-fn layout_new(layout_name: &OsStr, template: Option<&OsStr>, no_edit: bool) -> Result<()> {
+fn layout_new(
+    layout_name: &OsStr,
+    template: Option<&OsStr>,
+    no_edit: bool,
+    no_verify: bool,
+) -> Result<()> {
     let layout = if let Some(template) = template {
         // Open appropriate reader
         let stdin_;
@@ -273,11 +422,58 @@ fn layout_new(layout_name: &OsStr, template: Option<&OsStr>, no_edit: bool) -> R
     // Open config file for editing
     if !no_edit {
         open_editor(&layout)?;
+        if !no_verify {
+            verify_configfile(&layout)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn layout_save(layout_name: &OsStr, workspace: Option<&str>) -> Result<()> {
+    let mut i3 = I3Connection::connect()?;
+
+    let contents = layouts::capture_workspace(&mut i3, workspace)?;
+    let layout = Layout::create_from_template(layout_name, contents.as_bytes())?;
+    println!("Saved layout '{}'", layout.name);
+
+    Ok(())
+}
+
+fn fragment_new(fragment_name: &OsStr, no_edit: bool, no_verify: bool) -> Result<()> {
+    let fragment = Fragment::create_from_template(fragment_name, FRAGMENT_TEMPLATE)?;
+    println!("Created fragment '{}'", fragment.name);
+
+    // Open config file for editing
+    if !no_edit {
+        open_editor(&fragment)?;
+        if !no_verify {
+            verify_configfile(&fragment)?;
+        }
     }
 
     Ok(())
 }
 
+fn selection<'a>(only: &'a [String], skip: &'a [String]) -> Selection<'a> {
+    if !only.is_empty() {
+        Selection::Only(only)
+    } else if !skip.is_empty() {
+        Selection::Skip(skip)
+    } else {
+        Selection::All
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, s) => format!("{}h {}m {}s", h, m, s),
+    }
+}
+
 fn get_editor() -> Result<OsString> {
     env::var_os("VISUAL")
         .or_else(|| env::var_os("EDITOR"))
@@ -329,6 +525,15 @@ fn verify_configfile<C: ConfigFile>(configfile: &C) -> Result<()> {
         };
     }
 
+    let warnings = configfile.warnings();
+    if !warnings.is_empty() {
+        println!();
+        println!("Warnings:");
+        for warning in warnings {
+            println!("  {}", warning);
+        }
+    }
+
     Ok(())
 }
 
@@ -347,13 +552,22 @@ fn run() -> Result<()> {
             cli::ProjectCommands::Edit { name, no_verify } => {
                 command_edit::<Project>(name, *no_verify)
             }
-            cli::ProjectCommands::Info { name } => command_info::<Project>(name),
+            cli::ProjectCommands::Info { name } => project_info(name),
             cli::ProjectCommands::List { quiet } => command_list::<Project>(*quiet),
             cli::ProjectCommands::Local {
                 file,
                 working_directory,
                 workspace,
-            } => project_local(file, working_directory.as_deref(), workspace.as_deref()),
+                variables,
+                only,
+                skip,
+            } => project_local(
+                file,
+                working_directory.as_deref(),
+                workspace.as_deref(),
+                variables,
+                selection(only, skip),
+            ),
             cli::ProjectCommands::New {
                 name,
                 no_edit,
@@ -365,11 +579,34 @@ fn run() -> Result<()> {
                 edit,
                 no_verify,
             } => command_rename::<Project>(existing, new, *edit, *no_verify),
+            cli::ProjectCommands::Snapshot { name, workspace } => {
+                project_snapshot(name, workspace.as_deref())
+            }
             cli::ProjectCommands::Start {
                 name,
+                force,
+                restart,
+                timeout,
                 working_directory,
                 workspace,
-            } => project_start(name, working_directory.as_deref(), workspace.as_deref()),
+                variables,
+                only,
+                skip,
+            } => project_start(
+                name,
+                working_directory.as_deref(),
+                workspace.as_deref(),
+                variables,
+                selection(only, skip),
+                *force,
+                if *restart {
+                    Some(Duration::from_secs(*timeout))
+                } else {
+                    None
+                },
+            ),
+            cli::ProjectCommands::Status { name } => project_status(name.as_deref()),
+            cli::ProjectCommands::Stop { name, timeout } => project_stop(name, *timeout),
             cli::ProjectCommands::Verify { names } => project_verify(&names[..]),
         },
         cli::Commands::Layout(layout_commands) => match layout_commands {
@@ -377,21 +614,41 @@ fn run() -> Result<()> {
                 existing,
                 new,
                 no_edit,
-            } => command_copy::<Layout>(existing, new, *no_edit, false),
+                no_verify,
+            } => command_copy::<Layout>(existing, new, *no_edit, *no_verify),
             cli::LayoutCommands::Delete { names } => command_delete::<Layout, _>(&names[..]),
-            cli::LayoutCommands::Edit { name } => command_edit::<Layout>(name, false),
+            cli::LayoutCommands::Edit { name, no_verify } => {
+                command_edit::<Layout>(name, *no_verify)
+            }
             cli::LayoutCommands::Info { name } => command_info::<Layout>(name),
             cli::LayoutCommands::List { quiet } => command_list::<Layout>(*quiet),
             cli::LayoutCommands::New {
                 name,
                 no_edit,
+                no_verify,
                 template,
-            } => layout_new(name, template.as_deref(), *no_edit),
+            } => layout_new(name, template.as_deref(), *no_edit, *no_verify),
             cli::LayoutCommands::Rename {
                 existing,
                 new,
                 edit,
-            } => command_rename::<Layout>(existing, new, *edit, false),
+                no_verify,
+            } => command_rename::<Layout>(existing, new, *edit, *no_verify),
+            cli::LayoutCommands::Save { name, workspace } => {
+                layout_save(name, workspace.as_deref())
+            }
+        },
+        cli::Commands::Fragment(fragment_commands) => match fragment_commands {
+            cli::FragmentCommands::Delete { names } => command_delete::<Fragment, _>(&names[..]),
+            cli::FragmentCommands::Edit { name, no_verify } => {
+                command_edit::<Fragment>(name, *no_verify)
+            }
+            cli::FragmentCommands::List { quiet } => command_list::<Fragment>(*quiet),
+            cli::FragmentCommands::New {
+                name,
+                no_edit,
+                no_verify,
+            } => fragment_new(name, *no_edit, *no_verify),
         },
         cli::Commands::GenerateShellCompletions {
             generator,
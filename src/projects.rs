@@ -10,20 +10,36 @@
 
 use crate::{
     configfiles::{self, ConfigFile, ConfigFileImpl},
+    dotenv,
     errors::*,
-    layouts::Layout as ManagedLayout,
+    exec,
+    fragments::{self, Fragment as ManagedFragment},
+    layouts::{self, Layout as ManagedLayout},
+    shlex,
+    state::{self, ApplicationState, ProjectState},
+    tree,
     types::*,
+    variables, x11,
+};
+use i3ipc::{
+    event::{inner::WindowChange, Event, WindowEventInfo},
+    reply::Node,
+    I3Connection, I3EventListener, Subscription,
 };
-use i3ipc::I3Connection;
 use lazy_static::lazy_static;
+use regex::Regex;
 use std::{
+    collections::BTreeMap,
     ffi::{OsStr, OsString},
-    fs::File,
-    io::{prelude::*, BufReader},
+    fs::{self, File},
+    io::{self, prelude::*, BufReader},
+    net::{TcpStream, ToSocketAddrs},
     ops::Deref,
     path::{Path, PathBuf},
-    process::{Child, Command, Stdio},
-    time::Duration,
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
 };
 use tempfile::NamedTempFile;
 use wait_timeout::ChildExt;
@@ -32,6 +48,22 @@ lazy_static! {
     static ref PROJECTS_PREFIX: OsString = OsString::from("projects");
 }
 
+/// Selects which applications of a project are started, by their
+/// [`name`][fn-Application-name].
+///
+/// [fn-Application-name]: ../types/struct.Application.html#method.name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection<'a> {
+    /// Start all applications.
+    All,
+
+    /// Only start the applications with the given names.
+    Only(&'a [String]),
+
+    /// Start all applications except the ones with the given names.
+    Skip(&'a [String]),
+}
+
 /// A structure representing a `i3nator` project.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Project {
@@ -46,6 +78,7 @@ pub struct Project {
     pub path: PathBuf,
 
     config: Option<Config>,
+    variables: BTreeMap<String, String>,
 }
 
 impl Deref for Project {
@@ -66,6 +99,7 @@ impl Project {
             name,
             path,
             config: None,
+            variables: BTreeMap::new(),
         }
     }
 
@@ -73,7 +107,53 @@ impl Project {
         let mut file = BufReader::new(File::open(&self.path)?);
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
-        toml::from_str::<Config>(&contents).map_err(|e| e.into())
+
+        // Deserializing from a `toml::Value` loses the position of errors, which is why it is only
+        // done if other projects have to be merged or variables have to be interpolated.
+        let value = toml::from_str::<toml::Value>(&contents)?;
+        let (mut config, values) = if extends(&value).is_none()
+            && value.get("variables").is_none()
+            && self.variables.is_empty()
+        {
+            (toml::from_str::<Config>(&contents)?, BTreeMap::new())
+        } else {
+            let mut value = resolve_extends(value, &mut vec![self.name.to_owned()])?;
+            let values = variables::resolve(&value, &self.variables)?;
+            variables::interpolate(&mut value, &values);
+            let mut config = value.try_into::<Config>()?;
+            variables::interpolate_config_commands(&mut config, &values);
+            (config, values)
+        };
+
+        // Include the applications of all fragments, interpolating the variables of the project
+        for include in &config.include {
+            let path = match include.path {
+                Fragment::Managed(ref name) => ManagedFragment::open(name)?.path,
+                Fragment::Path(ref path) => resolve_path(path, self.path.parent()),
+            };
+            if !path.exists() {
+                return Err(ErrorKind::PathDoesntExist(path.to_string_lossy().into_owned()).into());
+            }
+            config
+                .applications
+                .extend(fragments::load(&path, &values)?.applications);
+        }
+
+        Ok(config)
+    }
+
+    /// Set the value of a variable declared in the `[variables]` table of the project, overriding
+    /// its default.
+    ///
+    /// The configuration will be reloaded the next time it is accessed.
+    ///
+    /// # Parameters:
+    ///
+    /// - `name`: The name of the variable.
+    /// - `value`: The value to use wherever the variable is referenced as `${name}`.
+    pub fn set_variable<S: Into<String>, V: Into<String>>(&mut self, name: S, value: V) {
+        self.variables.insert(name.into(), value.into());
+        self.config = None;
     }
 
     /// Gets the project's configuration, loading and storing it in the current project instance if
@@ -100,17 +180,51 @@ impl Project {
         Ok(self.config.as_ref().unwrap())
     }
 
+    /// Get the applications of the project in the order they are started in, i.e. every
+    /// application after its [`depends_on`][field-Application-depends_on] dependencies.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is:
+    ///
+    /// - `Ok`: the applications of all workspaces in the order they are started in.
+    /// - `Err`: an error, if the configuration is invalid or the dependencies of an application
+    ///   can't be satisfied.
+    ///
+    ///
+    /// [field-Application-depends_on]: ../types/struct.Application.html#structfield.depends_on
+    pub fn start_order(&mut self) -> Result<Vec<&Application>> {
+        let config = self.config()?;
+        Ok(schedule(&project_workspaces(config, None))?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
     /// Start the project.
     ///
     /// This will:
     ///
-    /// 1. append the specified layout to a given workspace,
-    /// 2. start the specified applications.
-    /// 3. execute commands in the applications, if specified.
+    /// 1. run the [`on_start`][field-General-on_start] hooks, aborting if any of them fails,
+    /// 2. append the specified layouts to their workspaces, moving the workspaces to their
+    ///    configured outputs,
+    /// 3. start the specified applications after their dependencies, waiting for each to be ready
+    ///    and, if necessary, to open a window (see
+    ///    [`Application::waits_for_window`][fn-Application-waits_for_window]),
+    /// 4. execute commands in the applications, if specified.
+    /// 5. record the started processes and created containers in the runtime state of the
+    ///    project as they are started, such that it can be stopped later on, even if starting it
+    ///    failed midway (see [`Project::stop`][fn-Project-stop]),
+    /// 6. run the [`after_start`][field-General-after_start] hooks.
     ///
-    /// Command execution is achieved through the use of [`xdotool`][xdotool], which in turn
-    /// simulates key-events through X11 in applications. This is not without problems, though.
-    /// Some applications do not react to `SendEvent`s, at least by default.
+    /// If the project is already running, the new instance will be tracked alongside the running
+    /// one. See [`Project::is_running`][fn-Project-is_running] if you want to avoid this.
+    ///
+    /// Command execution is achieved by focusing the window of the application and simulating
+    /// key-events through the XTEST extension of the X server. Alternatively,
+    /// [`xdotool`][xdotool] can be used (see [`Exec::backend`][field-Exec-backend]), which sends
+    /// the key-events to the window as `SendEvent`s. This is not without problems, though. Some
+    /// applications do not react to `SendEvent`s, at least by default.
     ///
     /// One example: in `xterm` you have to specifically enable for `SendEvent`s to be processed.
     /// This can be done through the the [`XTerm.vt100.allowSendEvents`][xterm-allow-send-events]
@@ -121,23 +235,38 @@ impl Project {
     /// - `i3`: An `I3Connection` to append the layout to a given workspace.
     /// - `working_directory`: An optional working directory which overrides any specified working
     /// directories in the project configuration.
-    /// - `workspace`: An optional workspace which overrides the specified workspace in the project
-    /// configuration.
+    /// - `workspace`: An optional workspace which overrides the specified workspace in the general
+    ///   section of the project configuration. Workspaces configured in `[[workspaces]]` are not
+    ///   affected.
+    /// - `selection`: Which applications to start. Applications that are not selected are not
+    ///   started, even if selected applications depend on them. The layouts are appended
+    ///   regardless.
     ///
     /// # Returns:
     ///
     /// A `Result` which is:
     ///
-    /// - `Ok`: nothing (`()`).
+    /// - `Ok`: a list of warnings, e.g. about applications that didn't open a window within their
+    ///   [`window_timeout`][field-Application-window_timeout] or failed `after_start` hooks.
     /// - `Err`: an error, if:
     ///
-    ///   - the configuration is invalid,
+    ///   - the configuration is invalid, e.g. the dependencies of an application are cyclic,
+    ///   - `selection` contains a name no application has,
+    ///   - an `on_start` hook failed,
     ///   - if a `layout` was specified but could not be stored in a temporary file,
     ///   - an i3-command failed,
     ///   - an application could not be started,
     ///   - a command could not be sent to an application.
     ///
     ///
+    /// [field-Application-window_timeout]:
+    ///   ../types/struct.Application.html#structfield.window_timeout
+    /// [field-Exec-backend]: ../types/struct.Exec.html#structfield.backend
+    /// [field-General-after_start]: ../types/struct.General.html#structfield.after_start
+    /// [field-General-on_start]: ../types/struct.General.html#structfield.on_start
+    /// [fn-Application-waits_for_window]: ../types/struct.Application.html#method.waits_for_window
+    /// [fn-Project-is_running]: #method.is_running
+    /// [fn-Project-stop]: #method.stop
     /// [xdotool]: https://github.com/jordansissel/xdotool
     /// [xterm-allow-send-events]: https://www.x.org/archive/X11R6.7.0/doc/xterm.1.html#sect6
     pub fn start(
@@ -145,73 +274,329 @@ impl Project {
         i3: &mut I3Connection,
         working_directory: Option<&OsStr>,
         workspace: Option<&str>,
-    ) -> Result<()> {
+        selection: Selection,
+    ) -> Result<Vec<String>> {
+        let working_directory_override = working_directory;
+        let name = self.name.to_owned();
         let config = self.config()?;
-        let general = &config.general;
-
-        // Determine if the layout is a path or the actual contents.
-        let mut tempfile;
-        let managed_layout_path;
-        let path: &Path = match general.layout {
-            Layout::Contents(ref contents) => {
-                tempfile = NamedTempFile::new()?;
-                tempfile.write_all(contents.as_bytes())?;
-                tempfile.flush()?;
-                tempfile.path()
-            }
-            Layout::Managed(ref name) => {
-                managed_layout_path = ManagedLayout::open(&name)?.path;
-                &managed_layout_path
-            }
-            Layout::Path(ref path) => path,
-        };
+        let mut state = ProjectState::new(name.to_owned());
+        let general_working_directory = working_directory_override
+            .map(Path::new)
+            .or(config.general.working_directory.as_deref());
 
-        // Change workspace if provided
-        let workspace = workspace
-            .map(Into::into)
-            .or_else(|| general.workspace.as_ref().cloned());
-        if let Some(ref workspace) = workspace {
-            i3.run_command(&format!("workspace {}", workspace))?;
+        // Determine the order to start the applications in before anything is started
+        let project_workspaces = project_workspaces(config, workspace);
+        let schedule = select(schedule(&project_workspaces)?, selection)?;
+
+        // Run the pre-start hooks, aborting the start if any of them fails
+        if let Some(ref hooks) = config.general.on_start {
+            run_hooks(hooks, &config.general, general_working_directory)?;
         }
 
-        // Append the layout to the workspace
-        i3.run_command(&format!(
-            "append_layout {}",
-            path.to_str()
-                .ok_or_else(|| ErrorKind::InvalidUtF8Path(path.to_string_lossy().into_owned()))?
-        ))?;
+        // Keep track of a previously started instance that is still running, but clean up the
+        // leftovers of one that isn't, e.g. placeholders that never swallowed a window.
+        if let Some(previous) = ProjectState::load(&name)? {
+            if is_state_running(i3, &previous)? {
+                state.containers = previous.containers;
+                state.applications = previous.applications;
+            } else {
+                close_containers(i3, &previous)?;
+            }
+        }
 
-        // Start the applications
-        let applications = &config.applications;
-        for application in applications {
-            let mut cmd = Command::new(&application.command.program);
-            cmd.args(&application.command.args);
+        // Listen for new windows before starting any application, such that none are missed
+        let window_events = if project_workspaces
+            .iter()
+            .flat_map(|project_workspace| project_workspace.applications)
+            .any(Application::waits_for_window)
+        {
+            Some((window_events()?, x11::Connection::connect()?))
+        } else {
+            None
+        };
+        let mut claims = WindowClaims::default();
+        let mut warnings = vec![];
 
-            // Get working directory. Precedence is as follows:
-            // 1. `--working-directory` command-line parameter
-            // 2. `working_directory` option in config for application
-            // 3. `working_directory` option in the general section of the config
-            let working_directory = working_directory
-                .map(OsStr::to_os_string)
-                .or_else(|| application.working_directory.as_ref().map(OsString::from))
-                .or_else(|| general.working_directory.as_ref().map(OsString::from));
+        for (project_workspace, applications) in project_workspaces.into_iter().zip(schedule) {
+            // Change workspace if provided
+            if let Some(ref workspace) = project_workspace.workspace {
+                i3.run_command(&format!("workspace {}", workspace))?;
+            }
+            let focused_workspace = tree::focused_workspace(i3)?;
 
-            if let Some(working_directory) = working_directory {
-                cmd.current_dir(working_directory);
+            // Move the workspace to the first available output
+            if !project_workspace.output.is_empty() {
+                match select_output(i3, project_workspace.output)? {
+                    Some(output) => {
+                        i3.run_command(&format!("move workspace to output \"{}\"", output))?;
+                    }
+                    None => warnings.push(format!(
+                        "none of the outputs '{}' are available, workspace '{}' was not moved",
+                        project_workspace.output.join("', '"),
+                        focused_workspace
+                    )),
+                }
             }
 
-            let child = cmd
-                .stdin(Stdio::null())
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
-                .spawn()?;
+            if !state.workspaces.contains(&focused_workspace) {
+                state.workspaces.push(focused_workspace);
+            }
 
-            // Input text into application, if any
-            if let Some(ref exec) = application.exec {
-                exec_commands(&child, exec)?;
+            // Append the layout to the workspace, remembering which containers it created
+            let placeholders = match project_workspace.layout {
+                Some(layout) => append_layout(i3, layout)?,
+                None => vec![],
+            };
+            for &id in &placeholders {
+                mark_container(i3, &name, id)?;
+                state.containers.push(id);
+            }
+            save_state(&state)?;
+
+            // Start the applications, every one after its dependencies
+            for application in applications {
+                let mut cmd = command(&application.command, application.shell.as_deref());
+
+                // Get working directory. Precedence is as follows:
+                // 1. `--working-directory` command-line parameter
+                // 2. `working_directory` option in config for application
+                // 3. `working_directory` option in the general section of the config
+                let working_directory = working_directory_override
+                    .map(OsStr::to_os_string)
+                    .or_else(|| application.working_directory.as_ref().map(OsString::from))
+                    .or_else(|| {
+                        config
+                            .general
+                            .working_directory
+                            .as_ref()
+                            .map(OsString::from)
+                    });
+
+                if let Some(ref working_directory) = working_directory {
+                    cmd.current_dir(working_directory);
+                }
+
+                // Set up the environment, the application's variables taking precedence. Relative
+                // env files are resolved against the respective working directory.
+                let working_directory = working_directory.as_deref().map(Path::new);
+                apply_general_env(&mut cmd, &config.general, general_working_directory)?;
+                for name in &application.unset_env {
+                    cmd.env_remove(name);
+                }
+                for path in &application.env_file {
+                    cmd.envs(dotenv::from_path(&resolve_path(path, working_directory))?);
+                }
+                cmd.envs(&application.env);
+
+                let child = cmd
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+
+                // Assign the windows opened so far to the applications that opened them, and
+                // wait for the application to open its window
+                let mut container = None;
+                if let Some((ref window_events, ref x11)) = window_events {
+                    let window_pid = |window: i32| x11.window_pid(window as x11::Window);
+                    claims.started(child.id());
+                    claim_windows(window_events, window_pid, &mut claims);
+                    if application.waits_for_window() {
+                        container = wait_for_window(
+                            window_events,
+                            window_pid,
+                            &mut claims,
+                            child.id(),
+                            &placeholders,
+                            application.window_timeout,
+                        );
+                        match container {
+                            Some(id) if !state.containers.contains(&id) => {
+                                mark_container(i3, &name, id)?;
+                                state.containers.push(id);
+                            }
+                            Some(_) => (),
+                            None => warnings.push(format!(
+                                "application '{}' didn't open a window within {}s",
+                                application.name(),
+                                application.window_timeout.as_secs()
+                            )),
+                        }
+                    }
+                }
+
+                state.applications.push(ApplicationState {
+                    program: application.command.program.to_owned(),
+                    pid: child.id(),
+                    start_time: state::process_start_time(child.id()),
+                    container,
+                });
+
+                // Record the application right away, such that it can be stopped even if starting
+                // the remaining applications fails
+                save_state(&state)?;
+
+                // Input text into application, if any
+                if let Some(ref exec) = application.exec {
+                    let title_matches = |title: &Regex| match (&window_events, container) {
+                        (Some((window_events, x11)), Some(container)) => wait_for_title(
+                            i3,
+                            window_events,
+                            |window: i32| x11.window_pid(window as x11::Window),
+                            &mut claims,
+                            container,
+                            title,
+                            exec.timeout,
+                        ),
+                        _ => Ok(false),
+                    };
+                    if let Some(title) =
+                        exec::exec_commands(child.id(), container, exec, title_matches)?
+                    {
+                        warnings.push(format!(
+                            "the window title of application '{}' didn't match '{}' within {}s, \
+                             its remaining commands were not input",
+                            application.name(),
+                            title,
+                            exec.timeout.as_secs()
+                        ));
+                    }
+                }
+
+                // Wait for the application to be ready before starting the next one
+                if let Some(condition) = wait_until_ready(application, working_directory) {
+                    warnings.push(format!(
+                        "application '{}' wasn't ready within {}s, {}",
+                        application.name(),
+                        application.ready_timeout.as_secs(),
+                        condition
+                    ));
+                }
+                thread::sleep(application.delay);
             }
         }
 
+        save_state(&state)?;
+
+        // Run the post-start hooks, the project is running regardless of them failing
+        if let Some(ref hooks) = config.general.after_start {
+            if let Err(e) = run_hooks(hooks, &config.general, general_working_directory) {
+                warnings.push(e.to_string());
+            }
+        }
+
+        Ok(warnings)
+    }
+
+    /// Stop the project.
+    ///
+    /// This will close everything that was recorded in the runtime state of the project when it
+    /// was started:
+    ///
+    /// 1. the containers created by the layout are closed through i3's `kill` command, if they
+    ///    still carry the mark they were given when the project was started,
+    /// 2. applications that did not exit within `grace_period` are sent a `SIGTERM`,
+    /// 3. applications that did not exit within another `grace_period` are sent a `SIGKILL`,
+    /// 4. the [`on_stop`][field-General-on_stop] hooks of the project are run.
+    ///
+    /// The runtime state is only deleted once all of these steps succeeded, such that stopping the
+    /// project can be retried otherwise.
+    ///
+    /// Processes are only signalled if they were started at the recorded time, such that processes
+    /// which reuse the process ID of an exited application are left alone.
+    ///
+    /// # Parameters:
+    ///
+    /// - `i3`: An `I3Connection` to close the containers of the project.
+    /// - `grace_period`: The duration to wait for the applications to exit before signalling
+    ///   them.
+    ///
+    /// # Returns:
+    ///
+    /// A `Result` which is:
+    ///
+    /// - `Ok`: nothing (`()`).
+    /// - `Err`: an error, if:
+    ///
+    ///   - the project is not running,
+    ///   - an i3-command failed,
+    ///   - the applications could not be signalled,
+    ///   - the configuration is invalid or an `on_stop` hook failed.
+    ///
+    ///
+    /// [field-General-on_stop]: ../types/struct.General.html#structfield.on_stop
+    pub fn stop(&self, i3: &mut I3Connection, grace_period: Duration) -> Result<()> {
+        let state = ProjectState::load(&self.name)?
+            .ok_or_else(|| ErrorKind::ProjectNotRunning(self.name.to_owned()))?;
+        // The configuration is loaded before anything is closed, such that the project can be
+        // stopped again if it is invalid
+        let config = self.load()?;
+
+        // Close the containers which still exist
+        close_containers(i3, &state)?;
+
+        // Fall back to signalling the applications if they don't exit on their own
+        if !wait_for_exit(&state, grace_period) {
+            signal(&state.alive_pids(), libc::SIGTERM)?;
+            if !wait_for_exit(&state, grace_period) {
+                signal(&state.alive_pids(), libc::SIGKILL)?;
+            }
+        }
+
+        // Run the stop hooks once the applications are gone. The state is only deleted once they
+        // succeeded, such that stopping the project can be retried.
+        if let Some(ref hooks) = config.general.on_stop {
+            run_hooks(
+                hooks,
+                &config.general,
+                config.general.working_directory.as_deref(),
+            )?;
+        }
+
+        state.delete()
+    }
+
+    /// Check whether the project is running.
+    ///
+    /// A project is considered to be running if any of the containers created when it was started
+    /// still holds a window, or if any of the applications started is still alive.
+    ///
+    /// # Parameters:
+    ///
+    /// - `i3`: An `I3Connection` to look up the containers of the project.
+    pub fn is_running(&self, i3: &mut I3Connection) -> Result<bool> {
+        match ProjectState::load(&self.name)? {
+            Some(state) => is_state_running(i3, &state),
+            None => Ok(false),
+        }
+    }
+
+    /// Focus the running project.
+    ///
+    /// This switches to the workspace the project was started on and focuses one of the windows
+    /// the project's layout swallowed.
+    ///
+    /// # Parameters:
+    ///
+    /// - `i3`: An `I3Connection` to focus the project with.
+    ///
+    /// # Returns:
+    ///
+    /// A `Result` which is:
+    ///
+    /// - `Ok`: nothing (`()`).
+    /// - `Err`: an error, if the project is not running or an i3-command failed.
+    pub fn focus(&self, i3: &mut I3Connection) -> Result<()> {
+        let state = ProjectState::load(&self.name)?
+            .ok_or_else(|| ErrorKind::ProjectNotRunning(self.name.to_owned()))?;
+
+        if let Some(workspace) = state.workspaces.first() {
+            i3.run_command(&format!("workspace {}", workspace))?;
+        }
+        if let Some(id) = window_containers(i3, &state)?.first() {
+            i3.run_command(&format!("[con_id={}] focus", id))?;
+        }
+
         Ok(())
     }
 }
@@ -266,17 +651,21 @@ impl ConfigFile for Project {
             paths.push(p);
         }
 
-        match config.general.layout {
-            Layout::Contents(_) => (),
-            Layout::Managed(ref name) => {
-                ManagedLayout::open(name)?;
-            }
-            Layout::Path(ref path) => paths.push(path),
+        // Verify that there is anything to start at all
+        let project_workspaces = project_workspaces(&config, None);
+        if project_workspaces.is_empty() {
+            return Err(ErrorKind::EmptyProject(self.name.to_owned()).into());
         }
 
-        for application in &config.applications {
-            if let Some(ref p) = application.working_directory {
-                paths.push(p);
+        for project_workspace in &project_workspaces {
+            if let Some(Layout::Path(ref path)) = project_workspace.layout {
+                paths.push(path);
+            }
+
+            for application in project_workspace.applications {
+                if let Some(ref p) = application.working_directory {
+                    paths.push(p);
+                }
             }
         }
 
@@ -287,9 +676,168 @@ impl ConfigFile for Project {
             }
         }
 
+        // Verify that all env files exist and can be parsed
+        let mut env_files = vec![];
+        for path in &config.general.env_file {
+            env_files.push(resolve_path(
+                path,
+                config.general.working_directory.as_deref(),
+            ));
+        }
+        for project_workspace in &project_workspaces {
+            for application in project_workspace.applications {
+                let working_directory = application
+                    .working_directory
+                    .as_ref()
+                    .or(config.general.working_directory.as_ref());
+                for path in &application.env_file {
+                    env_files.push(resolve_path(path, working_directory.map(PathBuf::as_path)));
+                }
+            }
+        }
+        for path in env_files {
+            if !path.exists() {
+                return Err(ErrorKind::PathDoesntExist(path.to_string_lossy().into_owned()).into());
+            }
+            dotenv::from_path(&path)?;
+        }
+
+        // Verify that the commands of all applications can be input
+        for project_workspace in &project_workspaces {
+            for exec in project_workspace
+                .applications
+                .iter()
+                .filter_map(|a| a.exec.as_ref())
+            {
+                exec::verify(exec)?;
+            }
+        }
+
+        // Verify that the dependencies of all applications can be satisfied
+        schedule(&project_workspaces)?;
+
+        // Verify that the layouts can be appended
+        for layout in project_workspaces.iter().filter_map(|w| w.layout) {
+            layouts::verify(&read_layout(layout)?)?;
+        }
+
         Ok(())
     }
 
+    fn warnings(&self) -> Vec<String> {
+        let config = match self.load() {
+            Ok(config) => config,
+            Err(_) => return vec![],
+        };
+        let project_workspaces = project_workspaces(&config, None);
+
+        let mut warnings = vec![];
+        let hooks = [
+            ("on_start", &config.general.on_start),
+            ("after_start", &config.general.after_start),
+            ("on_stop", &config.general.on_stop),
+        ];
+        for (name, hooks) in &hooks {
+            let hooks = match hooks {
+                Some(hooks) if hooks.shell.is_none() => hooks,
+                _ => continue,
+            };
+            for command in &hooks.commands {
+                if let Some(syntax) = shell_syntax(command) {
+                    warnings.push(format!(
+                        "{} hook '{}' contains shell syntax ('{}') but isn't run through a shell, \
+                         set `shell = true`",
+                        name,
+                        commandline(command),
+                        syntax
+                    ));
+                }
+            }
+        }
+
+        for project_workspace in &project_workspaces {
+            let applications = project_workspace.applications;
+
+            // Only mention the workspace if there are multiple
+            let prefix = match project_workspace.workspace {
+                Some(ref workspace) if project_workspaces.len() > 1 => {
+                    format!("workspace '{}': ", workspace)
+                }
+                _ => String::new(),
+            };
+
+            for application in applications.iter().filter(|a| a.shell.is_none()) {
+                if let Some(syntax) = shell_syntax(&application.command) {
+                    warnings.push(format!(
+                        "{}application '{}' contains shell syntax ('{}') but isn't run through a \
+                         shell, set `shell = true`",
+                        prefix,
+                        application.name(),
+                        syntax
+                    ));
+                }
+            }
+
+            for application in applications.iter().filter(|a| !a.waits_for_window()) {
+                let exec = match application.exec {
+                    Some(ref exec) => exec,
+                    None => continue,
+                };
+                let title = exec.wait_for.title.as_ref().or_else(|| {
+                    exec.steps.iter().find_map(|step| match *step {
+                        ExecStep::WaitTitle(ref title) => Some(title),
+                        _ => None,
+                    })
+                });
+                if let Some(title) = title {
+                    warnings.push(format!(
+                        "{}application '{}' waits for its window title to match '{}', but not for \
+                         its window, its commands won't be input",
+                        prefix,
+                        application.name(),
+                        title
+                    ));
+                }
+            }
+
+            let containers = match project_workspace
+                .layout
+                .map(|layout| read_layout(layout).and_then(|contents| layouts::parse(&contents)))
+            {
+                Some(Ok(containers)) => containers,
+                _ => continue,
+            };
+            let placeholders = layouts::placeholders(&containers);
+
+            if placeholders.len() != applications.len() {
+                warnings.push(format!(
+                    "{}the layout contains {} placeholder(s), but {} application(s) are started",
+                    prefix,
+                    placeholders.len(),
+                    applications.len()
+                ));
+            }
+
+            for placeholder in placeholders {
+                // A window is swallowed if any of the sets of criteria matches
+                let reasons = placeholder
+                    .criteria
+                    .iter()
+                    .map(|criteria| unmatchable_criteria(criteria, applications))
+                    .collect::<Option<Vec<_>>>();
+                if let Some(reason) = reasons.and_then(|reasons| reasons.into_iter().next()) {
+                    warnings.push(format!(
+                        "{}the placeholder on line {} of the layout likely won't swallow any \
+                         application: {}",
+                        prefix, placeholder.line, reason
+                    ));
+                }
+            }
+        }
+
+        warnings
+    }
+
     fn list() -> Vec<OsString> {
         configfiles::list(&*PROJECTS_PREFIX)
     }
@@ -307,6 +855,551 @@ impl ConfigFile for Project {
     }
 }
 
+/// A workspace the project is started on, i.e. either the one configured in the `general` section
+/// or one of the `[[workspaces]]`.
+struct ProjectWorkspace<'a> {
+    workspace: Option<String>,
+    output: &'a [String],
+    layout: Option<&'a Layout>,
+    applications: &'a [Application],
+}
+
+/// Determine the order the applications of every workspace are started in, such that every
+/// application is started after its dependencies.
+///
+/// Applications keep the order they are defined in, unless they have to wait for a dependency.
+fn schedule<'a>(project_workspaces: &[ProjectWorkspace<'a>]) -> Result<Vec<Vec<&'a Application>>> {
+    // Remember on which workspaces the applications are started
+    let mut workspaces: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (index, project_workspace) in project_workspaces.iter().enumerate() {
+        for application in project_workspace.applications {
+            workspaces
+                .entry(application.name())
+                .or_default()
+                .push(index);
+        }
+    }
+
+    let mut schedule = vec![];
+    for (index, project_workspace) in project_workspaces.iter().enumerate() {
+        for application in project_workspace.applications {
+            for dependency in &application.depends_on {
+                let error = match workspaces.get(dependency.as_str()).map(Vec::as_slice) {
+                    None => format!("there is no application named '{}'", dependency),
+                    Some(&[workspace]) if workspace <= index => continue,
+                    Some(&[_]) => format!("'{}' is started on a later workspace", dependency),
+                    Some(_) => format!("multiple applications are named '{}'", dependency),
+                };
+                return Err(
+                    ErrorKind::InvalidDependency(application.name().to_owned(), error).into(),
+                );
+            }
+        }
+
+        // Repeatedly start the first application whose dependencies have all been started
+        let mut pending = project_workspace.applications.iter().collect::<Vec<_>>();
+        let mut started: Vec<&Application> = vec![];
+        while !pending.is_empty() {
+            let next = pending.iter().position(|application| {
+                application.depends_on.iter().all(|dependency| {
+                    workspaces[dependency.as_str()][0] < index
+                        || started.iter().any(|started| started.name() == dependency)
+                })
+            });
+            match next {
+                Some(position) => started.push(pending.remove(position)),
+                None => {
+                    return Err(ErrorKind::InvalidDependency(
+                        pending[0].name().to_owned(),
+                        format!(
+                            "cyclic dependency between '{}'",
+                            pending
+                                .iter()
+                                .map(|application| application.name())
+                                .collect::<Vec<_>>()
+                                .join("', '")
+                        ),
+                    )
+                    .into())
+                }
+            }
+        }
+        schedule.push(started);
+    }
+
+    Ok(schedule)
+}
+
+/// Remove the applications not matching `selection` from `schedule`.
+fn select<'a>(
+    schedule: Vec<Vec<&'a Application>>,
+    selection: Selection,
+) -> Result<Vec<Vec<&'a Application>>> {
+    let (names, only) = match selection {
+        Selection::All => return Ok(schedule),
+        Selection::Only(names) => (names, true),
+        Selection::Skip(names) => (names, false),
+    };
+
+    if let Some(name) = names.iter().find(|name| {
+        !schedule
+            .iter()
+            .flatten()
+            .any(|application| application.name() == name.as_str())
+    }) {
+        return Err(ErrorKind::UnknownApplication(name.to_owned()).into());
+    }
+
+    Ok(schedule
+        .into_iter()
+        .map(|applications| {
+            applications
+                .into_iter()
+                .filter(|application| names.iter().any(|name| name == application.name()) == only)
+                .collect()
+        })
+        .collect())
+}
+
+fn project_workspaces<'a>(
+    config: &'a Config,
+    workspace: Option<&str>,
+) -> Vec<ProjectWorkspace<'a>> {
+    let mut project_workspaces = vec![];
+    if config.general.layout.is_some() || !config.applications.is_empty() {
+        project_workspaces.push(ProjectWorkspace {
+            workspace: workspace
+                .map(Into::into)
+                .or_else(|| config.general.workspace.clone()),
+            output: &config.general.output,
+            layout: config.general.layout.as_ref(),
+            applications: &config.applications,
+        });
+    }
+    project_workspaces.extend(config.workspaces.iter().map(|workspace| ProjectWorkspace {
+        workspace: Some(workspace.workspace.to_owned()),
+        output: &workspace.output,
+        layout: Some(&workspace.layout),
+        applications: &workspace.applications,
+    }));
+
+    project_workspaces
+}
+
+/// Select the first of the `outputs` which is active, where `primary` refers to the primary
+/// output.
+fn select_output(i3: &mut I3Connection, outputs: &[String]) -> Result<Option<String>> {
+    let available = i3
+        .get_outputs()?
+        .outputs
+        .into_iter()
+        .filter(|output| output.active)
+        .collect::<Vec<_>>();
+
+    Ok(outputs.iter().find_map(|name| {
+        available
+            .iter()
+            .find(|output| {
+                if name == "primary" {
+                    output.primary
+                } else {
+                    &output.name == name
+                }
+            })
+            .map(|output| output.name.to_owned())
+    }))
+}
+
+fn append_layout(i3: &mut I3Connection, layout: &Layout) -> Result<Vec<i64>> {
+    // Determine if the layout is a path or the actual contents.
+    let mut tempfile;
+    let managed_layout_path;
+    let path: &Path = match *layout {
+        Layout::Contents(ref contents) => {
+            tempfile = NamedTempFile::new()?;
+            tempfile.write_all(contents.as_bytes())?;
+            tempfile.flush()?;
+            tempfile.path()
+        }
+        Layout::Managed(ref name) => {
+            managed_layout_path = ManagedLayout::open(&name)?.path;
+            &managed_layout_path
+        }
+        Layout::Path(ref path) => path,
+    };
+
+    let existing_containers = tree::container_ids(&i3.get_tree()?);
+    i3.run_command(&format!(
+        "append_layout {}",
+        path.to_str()
+            .ok_or_else(|| ErrorKind::InvalidUtF8Path(path.to_string_lossy().into_owned()))?
+    ))?;
+
+    Ok(tree::leaf_container_ids(&i3.get_tree()?)
+        .into_iter()
+        .filter(|id| !existing_containers.contains(id))
+        .collect())
+}
+
+fn extends(value: &toml::Value) -> Option<&toml::Value> {
+    value
+        .get("general")
+        .and_then(|general| general.get("extends"))
+}
+
+/// Merge the configuration `value` into the configuration of the project it extends, following
+/// the chain of extended projects. `chain` contains the names of the projects already visited.
+fn resolve_extends(mut value: toml::Value, chain: &mut Vec<String>) -> Result<toml::Value> {
+    let extends = match extends(&value) {
+        Some(extends) => extends.clone().try_into::<Extends>()?,
+        None => return Ok(value),
+    };
+
+    let cyclic = chain.contains(&extends.name);
+    chain.push(extends.name.to_owned());
+    if cyclic {
+        return Err(ErrorKind::CyclicExtends(chain.join(" -> ")).into());
+    }
+
+    let project = Project::open(&extends.name)?;
+    let contents = fs::read_to_string(&project.path)?;
+    let mut base = resolve_extends(toml::from_str(&contents)?, chain)?;
+
+    // Lists are replaced as a whole when merging, so appending means prepending the lists of the
+    // extended project. Only the applications can be replaced instead.
+    for key in &["applications", "include", "workspaces"] {
+        let inherited = base.as_table_mut().and_then(|table| table.remove(*key));
+        if *key == "applications" && extends.applications == Inheritance::Replace {
+            continue;
+        }
+
+        if let (Some(toml::Value::Array(mut list)), Some(table)) = (inherited, value.as_table_mut())
+        {
+            match table.remove(*key) {
+                Some(toml::Value::Array(mut own)) => list.append(&mut own),
+                Some(own) => {
+                    table.insert((*key).to_owned(), own);
+                    continue;
+                }
+                None => (),
+            }
+            table.insert((*key).to_owned(), toml::Value::Array(list));
+        }
+    }
+
+    merge(&mut base, value);
+    Ok(base)
+}
+
+/// Merge `value` into `base`, merging tables recursively while all other values replace the ones
+/// in `base`.
+fn merge(base: &mut toml::Value, value: toml::Value) {
+    match (base, value) {
+        (toml::Value::Table(base), toml::Value::Table(table)) => {
+            for (key, value) in table {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+/// Apply the environment configured in the general section to `cmd`. Relative env files are
+/// resolved against `working_directory`.
+fn apply_general_env(
+    cmd: &mut Command,
+    general: &General,
+    working_directory: Option<&Path>,
+) -> Result<()> {
+    for name in &general.unset_env {
+        cmd.env_remove(name);
+    }
+    for path in &general.env_file {
+        cmd.envs(dotenv::from_path(&resolve_path(path, working_directory))?);
+    }
+    cmd.envs(&general.env);
+
+    Ok(())
+}
+
+/// Run the commands of `hooks` one after another, failing if any of them exits unsuccessfully or
+/// doesn't finish within the timeout.
+fn run_hooks(hooks: &Hooks, general: &General, working_directory: Option<&Path>) -> Result<()> {
+    for command in &hooks.commands {
+        let mut cmd = self::command(command, hooks.shell.as_deref());
+        if let Some(working_directory) = working_directory {
+            cmd.current_dir(working_directory);
+        }
+        apply_general_env(&mut cmd, general, working_directory)?;
+
+        let commandline = commandline(command);
+        let mut child = cmd
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(|e| ErrorKind::HookFailed(commandline.to_owned(), e.to_string()))?;
+
+        // Return of `wait_timeout` is `None` if the process didn't exit.
+        match child.wait_timeout(hooks.timeout)? {
+            Some(status) if status.success() => (),
+            Some(status) => {
+                return Err(ErrorKind::HookFailed(commandline, status.to_string()).into());
+            }
+            None => {
+                child.kill()?;
+                child.wait()?;
+                return Err(ErrorKind::HookFailed(
+                    commandline,
+                    format!("didn't finish within {}s", hooks.timeout.as_secs()),
+                )
+                .into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Create the `Command` running `command`, through `shell -c` if a shell is given.
+fn command(command: &ApplicationCommand, shell: Option<&Path>) -> Command {
+    match shell {
+        Some(shell) => {
+            let mut cmd = Command::new(shell);
+            cmd.arg("-c").arg(commandline(command));
+            cmd
+        }
+        None => {
+            let mut cmd = Command::new(&command.program);
+            cmd.args(&command.args);
+            cmd
+        }
+    }
+}
+
+/// Get the command line of `command`, i.e. the string it was configured as, or its program and
+/// arguments quoted for a POSIX shell.
+fn commandline(command: &ApplicationCommand) -> String {
+    if let Some(ref commandline) = command.commandline {
+        return commandline.to_owned();
+    }
+
+    ::std::iter::once(&command.program)
+        .chain(&command.args)
+        .map(|arg| shlex::quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Find the first word of a command configured as a string which contains shell syntax, e.g. a
+/// pipe, a redirection, a variable or a glob. Without a shell these are passed to the program as
+/// they are.
+///
+/// Quotes are taken into account like a shell would: within single quotes nothing is shell
+/// syntax, within double quotes only variables and command substitutions are.
+fn shell_syntax(command: &ApplicationCommand) -> Option<&str> {
+    const METACHARACTERS: &str = "|&;<>()$`*?[";
+    const QUOTED_METACHARACTERS: &str = "$`";
+
+    // Commands specified as a list of arguments are taken literally
+    let commandline = command.commandline.as_ref()?;
+
+    let mut start = None;
+    let mut quote = None;
+    let mut syntax = false;
+    for (index, c) in commandline.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some('"') if QUOTED_METACHARACTERS.contains(c) => syntax = true,
+            Some(_) => (),
+            None => match c {
+                ' ' | '\t' | '\n' => {
+                    if let Some(start) = start.take() {
+                        if syntax {
+                            return Some(&commandline[start..index]);
+                        }
+                    }
+                }
+                '\'' | '"' => {
+                    start.get_or_insert(index);
+                    quote = Some(c);
+                }
+                _ => {
+                    // Tilde expansion and comments only apply at the start of a word
+                    let word_start = *start.get_or_insert(index);
+                    if METACHARACTERS.contains(c) || (index == word_start && "~#".contains(c)) {
+                        syntax = true;
+                    }
+                }
+            },
+        }
+    }
+
+    match start {
+        Some(start) if syntax => Some(&commandline[start..]),
+        _ => None,
+    }
+}
+
+/// Wait for the [`wait_for_port`][field-Application-wait_for_port] and
+/// [`wait_for_file`][field-Application-wait_for_file] conditions of `application`, returning a
+/// description of the condition that wasn't met within the `ready_timeout`.
+///
+/// [field-Application-wait_for_file]: ../types/struct.Application.html#structfield.wait_for_file
+/// [field-Application-wait_for_port]: ../types/struct.Application.html#structfield.wait_for_port
+fn wait_until_ready(application: &Application, working_directory: Option<&Path>) -> Option<String> {
+    // Timeouts too long to compute a deadline for are as good as none
+    let deadline = Instant::now().checked_add(application.ready_timeout);
+    let timed_out = || matches!(deadline, Some(deadline) if Instant::now() >= deadline);
+
+    if let Some(ref address) = application.wait_for_port {
+        while !accepts_connections(address) {
+            if timed_out() {
+                return Some(format!("nothing is listening on '{}'", address));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    if let Some(ref path) = application.wait_for_file {
+        let path = resolve_path(path, working_directory);
+        while !path.exists() {
+            if timed_out() {
+                return Some(format!("'{}' doesn't exist", path.to_string_lossy()));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    None
+}
+
+fn accepts_connections(address: &str) -> bool {
+    address
+        .to_socket_addrs()
+        .map(|mut addresses| {
+            addresses.any(|address| {
+                TcpStream::connect_timeout(&address, Duration::from_millis(100)).is_ok()
+            })
+        })
+        .unwrap_or(false)
+}
+
+fn resolve_path(path: &Path, working_directory: Option<&Path>) -> PathBuf {
+    match working_directory {
+        Some(working_directory) if path.is_relative() => working_directory.join(path),
+        _ => path.to_owned(),
+    }
+}
+
+fn read_layout(layout: &Layout) -> Result<String> {
+    let path = match *layout {
+        Layout::Contents(ref contents) => return Ok(contents.to_owned()),
+        Layout::Managed(ref name) => ManagedLayout::open(name)?.path,
+        Layout::Path(ref path) => path.to_owned(),
+    };
+
+    let mut file = BufReader::new(File::open(path)?);
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// Check if none of the `applications` can obviously be matched by the swallow `criteria`,
+/// returning the reason if so.
+fn unmatchable_criteria(
+    criteria: &[(String, String)],
+    applications: &[Application],
+) -> Option<String> {
+    for (key, value) in criteria {
+        match key.as_str() {
+            "window_role" => {
+                let sets_role = applications.iter().any(|application| {
+                    application
+                        .command
+                        .args
+                        .iter()
+                        .any(|arg| arg.to_lowercase().contains("role"))
+                });
+                if !sets_role {
+                    return Some(format!(
+                        "no application sets a window role (e.g. using `--role`) to match \
+                         `window_role = {:?}`",
+                        value
+                    ));
+                }
+            }
+            "class" | "instance" => {
+                let literal = match regex_literal(value) {
+                    Some(literal) => literal,
+                    None => continue,
+                };
+                if !applications
+                    .iter()
+                    .any(|application| resembles(&application.command, &literal))
+                {
+                    return Some(format!(
+                        "no application resembles the {} '{}' (e.g. using `--class`)",
+                        key, literal
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    None
+}
+
+/// Get the literal a regular expression like `^URxvt$` matches, if it doesn't use any other
+/// regular expression features.
+fn regex_literal(regex: &str) -> Option<String> {
+    let regex = regex.strip_prefix('^').unwrap_or(regex);
+    let regex = regex.strip_suffix('$').unwrap_or(regex);
+
+    let mut literal = String::with_capacity(regex.len());
+    let mut chars = regex.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => literal.push(chars.next()?),
+            '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' => {
+                return None
+            }
+            ch => literal.push(ch),
+        }
+    }
+
+    Some(literal)
+}
+
+/// Check if the program or any of the arguments of `command` resemble the window property
+/// `literal`, e.g. `urxvt` resembles the class `URxvt`.
+fn resembles(command: &ApplicationCommand, literal: &str) -> bool {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|ch| ch.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    let literal = normalize(literal);
+    if literal.is_empty() {
+        return true;
+    }
+
+    let program = Path::new(&command.program)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    ::std::iter::once(program.as_str())
+        .chain(command.args.iter().flat_map(|arg| arg.split('=')))
+        .map(normalize)
+        .filter(|candidate| candidate.len() >= 3)
+        .any(|candidate| candidate.contains(&literal) || literal.contains(&candidate))
+}
+
 /// Get a list of all project names.
 ///
 /// This will check the current users XDG base directories for `i3nator` project configurations,
@@ -317,82 +1410,628 @@ pub fn list() -> Vec<OsString> {
     configfiles::list(&*PROJECTS_PREFIX)
 }
 
-fn exec_text(base_parameters: &[&str], text: &str, timeout: Duration) -> Result<()> {
-    let args = &[base_parameters, &["type", "--window", "%1", text]].concat();
-    let mut child = Command::new("xdotool")
-        .args(args)
-        .stdin(Stdio::null())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()?;
-
-    // Return of `wait_timeout` is `None` if the process didn't exit.
-    if child.wait_timeout(timeout)?.is_none() {
-        // Kill the xdotool process, return error
-        child.kill()?;
-        child.wait()?;
-        Err(ErrorKind::TextOrKeyInputFailed.into())
+/// Capture the applications running on a workspace as a project configuration.
+///
+/// The layout of the workspace is captured as described in
+/// [`layouts::capture_workspace`][fn-capture_workspace] and included in the configuration. For
+/// every window on the workspace, the process owning it is looked up through the window's
+/// `_NET_WM_PID` property, and its command line and working directory are used to configure an
+/// application. Windows for which no process can be determined are included as comments.
+///
+/// # Parameters
+///
+/// - `i3`: An `I3Connection` used to retrieve the current layout tree.
+/// - `workspace`: The name of the workspace to capture. If `None`, the currently focused
+///   workspace is used.
+///
+/// # Returns
+///
+/// A `Result` which is:
+///
+/// - `Ok`: the project configuration, which can be stored using
+///   [`Project::create_from_template`][fn-Project-create_from_template].
+/// - `Err`: an error, e.g. if the workspace does not exist.
+///
+///
+/// [fn-capture_workspace]: ../layouts/fn.capture_workspace.html
+/// [fn-Project-create_from_template]: struct.Project.html#method.create_from_template
+pub fn capture_workspace(i3: &mut I3Connection, workspace: Option<&str>) -> Result<String> {
+    let workspace = match workspace {
+        Some(workspace) => workspace.to_owned(),
+        None => tree::focused_workspace(i3)?,
+    };
+
+    let x11 = x11::Connection::connect()?;
+    let root = i3.get_tree()?;
+    let node = tree::find_workspace(&root, &workspace)
+        .ok_or_else(|| ErrorKind::UnknownWorkspace(workspace.to_owned()))?;
+
+    workspace_project(node, |window| x11.window_pid(window as x11::Window))
+}
+
+/// Convert the tree of a workspace into a project configuration, as described in
+/// [`capture_workspace`][fn-capture_workspace].
+///
+/// # Parameters
+///
+/// - `workspace`: The node of the workspace, e.g. as retrieved using `I3Connection::get_tree`.
+/// - `window_pid`: A function returning the process id owning the given X11 window, if it can be
+///   determined.
+///
+/// # Returns
+///
+/// A `Result` which is:
+///
+/// - `Ok`: the project configuration.
+/// - `Err`: an error, if the layout couldn't be serialized.
+///
+///
+/// [fn-capture_workspace]: fn.capture_workspace.html
+pub fn workspace_project<F>(workspace: &Node, mut window_pid: F) -> Result<String>
+where
+    F: FnMut(i32) -> Option<u32>,
+{
+    let layout = layouts::workspace_layout(workspace)?;
+    let workspace_name = workspace.name.to_owned().unwrap_or_default();
+
+    // Resolve the processes owning the windows on the workspace
+    let applications = tree::windows(workspace)
+        .into_iter()
+        .map(|node| {
+            let process = node.window.and_then(&mut window_pid).and_then(|pid| {
+                let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+                let working_directory = fs::read_link(format!("/proc/{}/cwd", pid)).ok();
+                let command = cmdline
+                    .split(|byte| *byte == 0)
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                    .collect::<Vec<_>>();
+                if command.is_empty() {
+                    None
+                } else {
+                    Some((command, working_directory))
+                }
+            });
+            (node.name.to_owned().unwrap_or_default(), process)
+        })
+        .collect::<Vec<_>>();
+    let working_directory = applications
+        .iter()
+        .find_map(|(_, process)| process.as_ref().and_then(|(_, wd)| wd.to_owned()));
+
+    // Assemble the configuration
+    let mut config = vec![
+        format!(
+            "# i3nator project, captured from workspace '{}'",
+            workspace_name
+        ),
+        "".to_owned(),
+        "[general]".to_owned(),
+    ];
+    if let Some(ref working_directory) = working_directory {
+        config.push(format!(
+            "working_directory = {}",
+            toml_string(&working_directory.to_string_lossy())
+        ));
+    }
+    config.push(format!("workspace = {}", toml_string(&workspace_name)));
+    if layout.contains("'''") {
+        config.push(format!("layout = {}", toml_string(&layout)));
     } else {
-        Ok(())
+        config.push(format!("layout = '''\n{}'''", layout));
     }
+
+    for (title, process) in applications {
+        config.push("".to_owned());
+        match process {
+            Some((command, application_working_directory)) => {
+                config.push("[[applications]]".to_owned());
+                config.push(format!(
+                    "command = {}",
+                    toml::Value::Array(command.into_iter().map(toml::Value::String).collect())
+                ));
+                if let Some(ref application_working_directory) = application_working_directory {
+                    if Some(application_working_directory) != working_directory.as_ref() {
+                        config.push(format!(
+                            "working_directory = {}",
+                            toml_string(&application_working_directory.to_string_lossy())
+                        ));
+                    }
+                }
+            }
+            None => {
+                config.push(format!(
+                    "# The command for the window {} could not be determined.",
+                    toml_string(&title)
+                ));
+                config.push("# [[applications]]".to_owned());
+                config.push("# command = \"mycommand\"".to_owned());
+            }
+        }
+    }
+    config.push("".to_owned());
+
+    Ok(config.join("\n"))
+}
+
+fn toml_string(value: &str) -> String {
+    toml::Value::String(value.to_owned()).to_string()
 }
 
-fn exec_keys<S: AsRef<OsStr>>(
-    base_parameters: &[&str],
-    keys: &[S],
+fn window_events() -> Result<Receiver<WindowEventInfo>> {
+    let mut listener = I3EventListener::connect()?;
+    listener.subscribe(&[Subscription::Window])?;
+
+    // Listening blocks indefinitely, which is why the events are forwarded from a separate thread.
+    // The thread exits on the first event after the receiver has been dropped.
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for event in listener.listen() {
+            if let Ok(Event::WindowEvent(info)) = event {
+                if sender.send(info).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(receiver)
+}
+
+/// The windows opened while starting a project, and the processes they were claimed for.
+#[derive(Debug, Default)]
+struct WindowClaims {
+    /// The processes started so far, and the container of the first window each of them opened.
+    processes: Vec<(u32, Option<i64>)>,
+    claimed: Vec<i64>,
+}
+
+impl WindowClaims {
+    fn started(&mut self, pid: u32) {
+        self.processes.push((pid, None));
+    }
+
+    fn container(&self, pid: u32) -> Option<i64> {
+        self.processes
+            .iter()
+            .find(|&&(process, _)| process == pid)
+            .and_then(|&(_, container)| container)
+    }
+
+    /// Claim a new `container` holding a window opened by the process `window_pid`.
+    ///
+    /// A window opened by one of the started processes always belongs to that process. Otherwise
+    /// it is only claimed for the process currently `waiting` for its window, if it was swallowed
+    /// by one of the placeholders of that process' layout. The latter is necessary for
+    /// applications which hand off opening their window to another process.
+    fn claim(
+        &mut self,
+        container: i64,
+        window_pid: Option<u32>,
+        waiting: Option<(u32, &[i64])>,
+    ) -> Option<u32> {
+        if self.claimed.contains(&container) {
+            return None;
+        }
+
+        let pid = window_pid
+            .filter(|&pid| self.processes.iter().any(|&(process, _)| process == pid))
+            .or_else(|| match waiting {
+                Some((pid, placeholders)) if placeholders.contains(&container) => Some(pid),
+                _ => None,
+            })?;
+        self.claimed.push(container);
+        for (_, claimed) in self
+            .processes
+            .iter_mut()
+            .filter(|(process, _)| *process == pid)
+        {
+            claimed.get_or_insert(container);
+        }
+
+        Some(pid)
+    }
+}
+
+/// Claim the windows opened so far for the processes that opened them, without waiting for any
+/// further windows.
+///
+/// This ensures that the windows of applications not waiting for them can't be mistaken for the
+/// window of an application started later on.
+fn claim_windows<F>(
+    window_events: &Receiver<WindowEventInfo>,
+    window_pid: F,
+    claims: &mut WindowClaims,
+) where
+    F: Fn(i32) -> Option<u32>,
+{
+    for info in window_events.try_iter() {
+        if info.change == WindowChange::New {
+            let pid = info.container.window.and_then(&window_pid);
+            claims.claim(info.container.id, pid, None);
+        }
+    }
+}
+
+/// Wait for the process `pid` to open a window, returning the container holding it.
+///
+/// `placeholders` are the containers created by the layout of the workspace the process was
+/// started on.
+fn wait_for_window<F>(
+    window_events: &Receiver<WindowEventInfo>,
+    window_pid: F,
+    claims: &mut WindowClaims,
+    pid: u32,
+    placeholders: &[i64],
     timeout: Duration,
-) -> Result<()> {
-    let args = &[base_parameters, &["key", "--window", "%1"]].concat();
-    let mut child = Command::new("xdotool")
-        .args(args)
-        .args(keys)
-        .stdin(Stdio::null())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()?;
-
-    // Return of `wait_timeout` is `None` if the process didn't exit.
-    if child.wait_timeout(timeout)?.is_none() {
-        // Kill the xdotool process, return error
-        child.kill()?;
-        child.wait()?;
-        Err(ErrorKind::TextOrKeyInputFailed.into())
-    } else {
-        Ok(())
+) -> Option<i64>
+where
+    F: Fn(i32) -> Option<u32>,
+{
+    // The window might have been opened before
+    if let Some(container) = claims.container(pid) {
+        return Some(container);
+    }
+
+    let deadline = Instant::now().checked_add(timeout);
+    loop {
+        let info = next_window_event(window_events, deadline)?;
+        if info.change != WindowChange::New {
+            continue;
+        }
+
+        let container = info.container;
+        let window = container.window.and_then(&window_pid);
+        if claims.claim(container.id, window, Some((pid, placeholders))) == Some(pid) {
+            return claims.container(pid);
+        }
     }
 }
 
-fn exec_commands(child: &Child, exec: &Exec) -> Result<()> {
-    let timeout = exec.timeout;
-    let pid = child.id().to_string();
-    let base_parameters = &[
-        "search",
-        "--sync",
-        "--onlyvisible",
-        "--any",
-        "--pid",
-        &pid,
-        "ignorepattern",
-        "windowfocus",
-        "--sync",
-        "%1",
-    ];
+/// Receive the next window event, waiting until `deadline`, or indefinitely if there is none.
+fn next_window_event(
+    window_events: &Receiver<WindowEventInfo>,
+    deadline: Option<Instant>,
+) -> Option<WindowEventInfo> {
+    match deadline {
+        Some(deadline) => {
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            window_events.recv_timeout(remaining).ok()
+        }
+        None => window_events.recv().ok(),
+    }
+}
+
+/// Wait for the title of the window in `container` to match `title`, returning whether it did
+/// within `timeout`.
+fn wait_for_title<F>(
+    i3: &mut I3Connection,
+    window_events: &Receiver<WindowEventInfo>,
+    window_pid: F,
+    claims: &mut WindowClaims,
+    container: i64,
+    title: &Regex,
+    timeout: Duration,
+) -> Result<bool>
+where
+    F: Fn(i32) -> Option<u32>,
+{
+    // The title might have been set before the window was found
+    if tree::nodes(&i3.get_tree()?).into_iter().any(|node| {
+        node.id == container && matches!(node.name, Some(ref name) if title.is_match(name))
+    }) {
+        return Ok(true);
+    }
+
+    Ok(wait_for_title_change(
+        window_events,
+        window_pid,
+        claims,
+        container,
+        title,
+        timeout,
+    ))
+}
 
-    let commands = &exec.commands;
-    match exec.exec_type {
-        ExecType::Text => {
-            for command in commands {
-                exec_text(base_parameters, command, timeout)?;
-                exec_keys(base_parameters, &["Return"], timeout)?;
+/// Wait for the title of the window in `container` to change to one matching `title`, returning
+/// whether it did within `timeout`.
+///
+/// Windows opened by other applications in the meantime are claimed for them, as described for
+/// [`claim_windows`](fn.claim_windows.html).
+fn wait_for_title_change<F>(
+    window_events: &Receiver<WindowEventInfo>,
+    window_pid: F,
+    claims: &mut WindowClaims,
+    container: i64,
+    title: &Regex,
+    timeout: Duration,
+) -> bool
+where
+    F: Fn(i32) -> Option<u32>,
+{
+    let deadline = Instant::now().checked_add(timeout);
+    loop {
+        let info = match next_window_event(window_events, deadline) {
+            Some(info) => info,
+            None => return false,
+        };
+        match info.change {
+            WindowChange::New => {
+                let pid = info.container.window.and_then(&window_pid);
+                claims.claim(info.container.id, pid, None);
             }
-        }
-        ExecType::TextNoReturn => {
-            for command in commands {
-                exec_text(base_parameters, command, timeout)?;
+            WindowChange::Title
+                if info.container.id == container
+                    && matches!(info.container.name, Some(ref name) if title.is_match(name)) =>
+            {
+                return true;
             }
+            _ => (),
         }
-        ExecType::Keys => exec_keys(base_parameters, commands.as_slice(), timeout)?,
+    }
+}
+
+/// Get the mark identifying the container `id` as created by the project `name`.
+///
+/// i3 reuses the IDs of closed containers, which is why containers are only considered to belong
+/// to the project if they still carry this mark. Marks starting with an underscore are not shown
+/// by i3, and the project name is encoded such that the mark can be matched literally by
+/// `con_mark`.
+fn container_mark(name: &str, id: i64) -> String {
+    let name = name
+        .bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => (byte as char).to_string(),
+            byte => format!("%{:02x}", byte),
+        })
+        .collect::<String>();
+    format!("_i3nator:{}:{}", name, id)
+}
+
+fn mark_container(i3: &mut I3Connection, name: &str, id: i64) -> Result<()> {
+    i3.run_command(&format!(
+        "[con_id={}] mark --add {}",
+        id,
+        container_mark(name, id)
+    ))?;
+
+    Ok(())
+}
+
+/// Get the containers recorded in `state` which still carry the mark of the project.
+fn marked_containers(i3: &mut I3Connection, state: &ProjectState) -> Result<Vec<i64>> {
+    let marks = i3.get_marks()?.marks;
+    Ok(state
+        .containers
+        .iter()
+        .cloned()
+        .filter(|&id| marks.contains(&container_mark(&state.name, id)))
+        .collect())
+}
+
+fn window_containers(i3: &mut I3Connection, state: &ProjectState) -> Result<Vec<i64>> {
+    let marked_containers = marked_containers(i3, state)?;
+    Ok(tree::nodes(&i3.get_tree()?)
+        .into_iter()
+        .filter(|node| node.window.is_some() && marked_containers.contains(&node.id))
+        .map(|node| node.id)
+        .collect())
+}
+
+fn is_state_running(i3: &mut I3Connection, state: &ProjectState) -> Result<bool> {
+    Ok(state.is_running() || !window_containers(i3, state)?.is_empty())
+}
+
+fn close_containers(i3: &mut I3Connection, state: &ProjectState) -> Result<()> {
+    for id in marked_containers(i3, state)? {
+        i3.run_command(&format!(
+            "[con_mark=\"^{}$\"] kill",
+            container_mark(&state.name, id)
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Persist `state`, if the runtime state of projects can be recorded at all.
+fn save_state(state: &ProjectState) -> Result<()> {
+    if state::is_available() {
+        state.save()?;
     }
 
     Ok(())
 }
+
+fn wait_for_exit(state: &ProjectState, timeout: Duration) -> bool {
+    let start = Instant::now();
+    while !state.alive_pids().is_empty() {
+        if start.elapsed() >= timeout {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    true
+}
+
+fn signal(pids: &[u32], signal: libc::c_int) -> Result<()> {
+    let mut errors = vec![];
+    for &pid in pids {
+        if unsafe { libc::kill(pid as libc::pid_t, signal) } == -1 {
+            // The process might have exited in the meantime
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(libc::ESRCH) {
+                errors.push(format!("{}: {}", pid, error));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ErrorKind::SignalFailed(errors.join(", ")).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use i3ipc::reply::{NodeBorder, NodeLayout, NodeType};
+
+    fn new_window(id: i64, window: i32) -> WindowEventInfo {
+        WindowEventInfo {
+            change: WindowChange::New,
+            container: Node {
+                focus: vec![],
+                nodes: vec![],
+                floating_nodes: vec![],
+                id,
+                name: None,
+                nodetype: NodeType::Con,
+                border: NodeBorder::Normal,
+                current_border_width: 2,
+                layout: NodeLayout::SplitH,
+                percent: None,
+                rect: (0, 0, 800, 600),
+                window_rect: (0, 0, 0, 0),
+                deco_rect: (0, 0, 0, 0),
+                geometry: (0, 0, 0, 0),
+                window: Some(window),
+                window_properties: None,
+                urgent: false,
+                focused: false,
+            },
+        }
+    }
+
+    fn title_changed(id: i64, window: i32, title: &str) -> WindowEventInfo {
+        let mut info = new_window(id, window);
+        info.change = WindowChange::Title;
+        info.container.name = Some(title.to_owned());
+        info
+    }
+
+    #[test]
+    fn container_marks() {
+        assert_eq!(container_mark("project", 42), "_i3nator:project:42");
+        assert_eq!(
+            container_mark("my project.v2", 42),
+            "_i3nator:my%20project%2ev2:42"
+        );
+        assert_ne!(container_mark("a b", 1), container_mark("a_b", 1));
+    }
+
+    #[test]
+    fn wait_for_window_after_non_waiting_application() {
+        // Windows 1 and 2 are opened by the processes 100 and 200, window 3 by an unknown process
+        let window_pid = |window: i32| match window {
+            1 => Some(100),
+            2 => Some(200),
+            _ => None,
+        };
+        let placeholders = [10, 30];
+        let timeout = Duration::from_millis(100);
+        let (sender, receiver) = mpsc::channel();
+        let mut claims = WindowClaims::default();
+
+        // The first application doesn't wait for its window, which is swallowed by a placeholder
+        claims.started(100);
+        claim_windows(&receiver, window_pid, &mut claims);
+        sender.send(new_window(10, 1)).unwrap();
+
+        // The second application waits for its window, which opens after the first one's
+        claims.started(200);
+        claim_windows(&receiver, window_pid, &mut claims);
+        sender.send(new_window(20, 2)).unwrap();
+        assert_eq!(
+            wait_for_window(
+                &receiver,
+                window_pid,
+                &mut claims,
+                200,
+                &placeholders,
+                timeout
+            ),
+            Some(20)
+        );
+        assert_eq!(claims.container(100), Some(10));
+
+        // A window of an unknown process is only claimed if it was swallowed by a placeholder
+        claims.started(300);
+        claim_windows(&receiver, window_pid, &mut claims);
+        sender.send(new_window(40, 4)).unwrap();
+        sender.send(new_window(10, 3)).unwrap();
+        sender.send(new_window(30, 3)).unwrap();
+        assert_eq!(
+            wait_for_window(
+                &receiver,
+                window_pid,
+                &mut claims,
+                300,
+                &placeholders,
+                timeout
+            ),
+            Some(30)
+        );
+
+        // Windows are only waited for until the timeout
+        claims.started(400);
+        assert_eq!(
+            wait_for_window(
+                &receiver,
+                window_pid,
+                &mut claims,
+                400,
+                &placeholders,
+                timeout
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn wait_for_title_claims_windows() {
+        let window_pid = |window: i32| match window {
+            1 => Some(100),
+            2 => Some(200),
+            _ => None,
+        };
+        let title = Regex::new(r"\$ $").unwrap();
+        let timeout = Duration::from_millis(100);
+        let (sender, receiver) = mpsc::channel();
+        let mut claims = WindowClaims::default();
+
+        // The first application waits for its title while the second one opens its window
+        claims.started(100);
+        claims.started(200);
+        claim_windows(&receiver, window_pid, &mut claims);
+        sender.send(title_changed(10, 1, "loading")).unwrap();
+        sender.send(new_window(20, 2)).unwrap();
+        sender.send(title_changed(20, 2, "~ $ ")).unwrap();
+        sender.send(title_changed(10, 1, "~ $ ")).unwrap();
+        assert!(wait_for_title_change(
+            &receiver,
+            window_pid,
+            &mut claims,
+            10,
+            &title,
+            timeout
+        ));
+
+        // The window of the second application is known without receiving it again
+        assert_eq!(
+            wait_for_window(&receiver, window_pid, &mut claims, 200, &[], timeout),
+            Some(20)
+        );
+
+        // Titles are only waited for until the timeout
+        assert!(!wait_for_title_change(
+            &receiver,
+            window_pid,
+            &mut claims,
+            10,
+            &title,
+            timeout
+        ));
+    }
+}
@@ -111,3 +111,18 @@ pub fn split<'a>(in_str: &'a str) -> Option<Vec<&'a str>> {
         Some(res)
     }
 }
+
+/// Quote `arg` for a POSIX shell, such that the shell passes it on as a single literal argument.
+///
+/// Arguments consisting only of characters without a special meaning are left unquoted.
+pub fn quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "%+,-./:=@_".contains(c));
+    if safe {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright Pit Kleyersburg <pitkley@googlemail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified or distributed
+// except according to those terms.
+
+//! Module for tracking the runtime state of started projects.
+//!
+//! When a project is started, i3nator records which processes and containers it created in the
+//! XDG runtime directory (usually `$XDG_RUNTIME_DIR/i3nator`). This allows to tear down a project
+//! again later on.
+
+use crate::{configfiles::XDG_DIRS, errors::*};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{prelude::*, BufReader},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tempfile::NamedTempFile;
+
+/// The runtime state of a started project.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProjectState {
+    /// The name of the project this state belongs to.
+    pub name: String,
+
+    /// The names of the workspaces the project was started on.
+    pub workspaces: Vec<String>,
+
+    /// The time the project was started at, in seconds since the UNIX epoch.
+    pub started: u64,
+
+    /// The IDs of the i3 containers created when appending the project's layout.
+    pub containers: Vec<i64>,
+
+    /// The applications started for the project.
+    pub applications: Vec<ApplicationState>,
+}
+
+/// The runtime state of an application started for a project.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ApplicationState {
+    /// The program that was started.
+    pub program: String,
+
+    /// The process ID of the started application.
+    pub pid: u32,
+
+    /// The time the application was started at, in clock ticks since boot, as reported by
+    /// `/proc/<pid>/stat`.
+    ///
+    /// Process IDs are reused, e.g. after a reboot, which is why a process is only considered to
+    /// be the application if it was started at the same time.
+    #[serde(default)]
+    pub start_time: Option<u64>,
+
+    /// The ID of the i3 container holding the window opened by the application, if it opened one.
+    #[serde(default)]
+    pub container: Option<i64>,
+}
+
+impl ApplicationState {
+    /// Check whether the application is still running.
+    ///
+    /// Applications recorded without their start time are never considered to be running.
+    pub fn is_alive(&self) -> bool {
+        match self.start_time {
+            Some(start_time) => is_process_alive(self.pid, start_time),
+            None => false,
+        }
+    }
+}
+
+impl ProjectState {
+    /// Create an empty state for the project with the given `name`, started now.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        ProjectState {
+            name: name.into(),
+            workspaces: vec![],
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            containers: vec![],
+            applications: vec![],
+        }
+    }
+
+    /// Load the state of the project with the given `name`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is:
+    ///
+    /// - `Ok`: the state of the project if it has been recorded, `None` otherwise.
+    /// - `Err`: an error, e.g. if the state could not be read or parsed.
+    pub fn load(name: &str) -> Result<Option<Self>> {
+        match XDG_DIRS.find_runtime_file(state_path(name)) {
+            Some(path) => {
+                let mut file = BufReader::new(File::open(path)?);
+                let mut contents = String::new();
+                file.read_to_string(&mut contents)?;
+                Ok(Some(toml::from_str::<ProjectState>(&contents)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Load the states of all projects that have been recorded.
+    ///
+    /// Every state is loaded on its own, such that a state that can't be read doesn't prevent the
+    /// others from being listed.
+    ///
+    /// # Returns
+    ///
+    /// The names of the projects, sorted, along with a `Result` which is:
+    ///
+    /// - `Ok`: the state of the project.
+    /// - `Err`: an error, e.g. if the state could not be read or parsed.
+    pub fn list() -> Vec<(String, Result<Self>)> {
+        let mut files = XDG_DIRS.list_runtime_files("projects");
+        files.sort();
+        files
+            .iter()
+            .filter_map(|file| file.file_stem())
+            .map(|name| name.to_string_lossy().into_owned())
+            .filter_map(|name| match ProjectState::load(&name) {
+                Ok(state) => state.map(|state| (name, Ok(state))),
+                Err(error) => Some((name, Err(error))),
+            })
+            .collect()
+    }
+
+    /// Persist this state in the XDG runtime directory, overwriting any previously recorded state
+    /// for the same project.
+    ///
+    /// The state is written to a temporary file first, which then replaces the recorded state,
+    /// such that a state is never read while it is only partially written.
+    pub fn save(&self) -> Result<()> {
+        let path = XDG_DIRS.place_runtime_file(state_path(&self.name))?;
+        let mut file = NamedTempFile::new_in(path.parent().unwrap_or_else(|| Path::new(".")))?;
+        file.write_all(toml::to_string(self)?.as_bytes())?;
+        file.flush()?;
+        file.persist(path).map_err(|error| error.error)?;
+
+        Ok(())
+    }
+
+    /// Remove the persisted state of this project.
+    pub fn delete(&self) -> Result<()> {
+        if let Some(path) = XDG_DIRS.find_runtime_file(state_path(&self.name)) {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the process IDs of all applications that are still running.
+    pub fn alive_pids(&self) -> Vec<u32> {
+        self.applications
+            .iter()
+            .filter(|application| application.is_alive())
+            .map(|application| application.pid)
+            .collect()
+    }
+
+    /// Check whether any of the applications of the project is still running.
+    pub fn is_running(&self) -> bool {
+        self.applications
+            .iter()
+            .any(|application| application.is_alive())
+    }
+
+    /// Get the duration that has passed since the project was started.
+    pub fn uptime(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH + Duration::from_secs(self.started))
+            .unwrap_or_default()
+    }
+}
+
+/// Check whether the runtime state of projects can be recorded.
+///
+/// This requires the XDG runtime directory to be available, i.e. `$XDG_RUNTIME_DIR` to be set.
+pub fn is_available() -> bool {
+    XDG_DIRS.has_runtime_directory()
+}
+
+/// Check whether the process with the given `pid`, started at `start_time`, is running.
+pub fn is_process_alive(pid: u32, start_time: u64) -> bool {
+    process_start_time(pid) == Some(start_time)
+}
+
+/// Get the time the process with the given `pid` was started at, in clock ticks since boot.
+///
+/// # Returns
+///
+/// The start time of the process, or `None` if no process with the given `pid` is running.
+pub fn process_start_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(Path::new("/proc").join(pid.to_string()).join("stat")).ok()?;
+
+    // The second field is the parenthesized name of the executable, which can contain spaces and
+    // parentheses itself. The start time is the 22nd field.
+    stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .nth(19)?
+        .parse()
+        .ok()
+}
+
+fn state_path(name: &str) -> PathBuf {
+    Path::new("projects").join(format!("{}.toml", name))
+}
@@ -0,0 +1,71 @@
+// Copyright Pit Kleyersburg <pitkley@googlemail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified or distributed
+// except according to those terms.
+
+use crate::errors::*;
+use i3ipc::{
+    reply::{Node, NodeType},
+    I3Connection,
+};
+
+/// Iterate over all nodes of the given tree, including the root itself and floating nodes.
+pub fn nodes(root: &Node) -> Vec<&Node> {
+    let mut nodes = vec![root];
+    let mut index = 0;
+    while index < nodes.len() {
+        let node = nodes[index];
+        nodes.extend(node.nodes.iter());
+        nodes.extend(node.floating_nodes.iter());
+        index += 1;
+    }
+
+    nodes
+}
+
+/// Get all containers holding a window, in the order they appear in the layout.
+pub fn windows(root: &Node) -> Vec<&Node> {
+    let mut windows = vec![];
+    if root.window.is_some() {
+        windows.push(root);
+    }
+    for node in root.nodes.iter().chain(root.floating_nodes.iter()) {
+        windows.extend(self::windows(node));
+    }
+
+    windows
+}
+
+/// Get the IDs of all containers without any children, e.g. windows or layout placeholders.
+pub fn leaf_container_ids(root: &Node) -> Vec<i64> {
+    nodes(root)
+        .into_iter()
+        .filter(|node| node.nodes.is_empty() && node.floating_nodes.is_empty())
+        .map(|node| node.id)
+        .collect()
+}
+
+/// Get the IDs of all containers in the given tree.
+pub fn container_ids(root: &Node) -> Vec<i64> {
+    nodes(root).into_iter().map(|node| node.id).collect()
+}
+
+/// Find the workspace with the given `name` in the given tree.
+pub fn find_workspace<'a>(root: &'a Node, name: &str) -> Option<&'a Node> {
+    nodes(root)
+        .into_iter()
+        .find(|node| node.nodetype == NodeType::Workspace && node.name.as_deref() == Some(name))
+}
+
+/// Get the name of the currently focused workspace.
+pub fn focused_workspace(i3: &mut I3Connection) -> Result<String> {
+    i3.get_workspaces()?
+        .workspaces
+        .into_iter()
+        .find(|workspace| workspace.focused)
+        .map(|workspace| workspace.name)
+        .ok_or_else(|| ErrorKind::UnknownWorkspace("focused workspace".to_owned()).into())
+}
@@ -20,9 +20,15 @@
 //! # Working directory to use
 //! working_directory = "/path/to/my/working/directory"
 //!
+//! # Environment variables to set for all applications
+//! env = { RUST_LOG = "debug" }
+//!
 //! # Name of the workspace the layout should be applied to
 //! workspace = "1"
 //!
+//! # Outputs to move the workspace to, the first available one is used
+//! output = "DP-1 primary"
+//!
 //! # Path to your layout-file
 //! layout_path = "/path/to/my/layout.json"
 //!
@@ -33,9 +39,25 @@
 //! [[applications]]
 //! command = "mycommand --with 'multiple args'"
 //! working_directory = "/path/to/a/different/working/directory"
+//!
+//! # Additional workspaces, each with its own layout and applications
+//! [[workspaces]]
+//! workspace = "2"
+//! layout = "/path/to/another/layout.json"
+//!
+//! [[workspaces.applications]]
+//! command = "myothercommand"
 //! ```
+//!
+//! Paths, i.e. working directories, env files and layout paths, are expanded the way a shell would:
+//! a leading `~` or `~user` refers to the respective home directory, and environment variables can
+//! be used as `$NAME` or `${NAME}`.
 
-use crate::{configfiles::ConfigFile, layouts::Layout as ManagedLayout, shlex};
+use crate::{
+    configfiles::ConfigFile, fragments::Fragment as ManagedFragment,
+    layouts::Layout as ManagedLayout, shlex,
+};
+use regex::Regex;
 use serde::{
     de::{self, Deserializer},
     Deserialize,
@@ -43,11 +65,14 @@ use serde::{
 #[cfg(unix)]
 use std::os::unix::ffi::OsStrExt;
 use std::{
-    borrow::Cow,
-    ffi::{OsStr, OsString},
+    collections::BTreeMap,
+    env,
+    ffi::{CStr, CString, OsStr, OsString},
     fmt,
     marker::PhantomData,
+    mem,
     path::{Path, PathBuf},
+    ptr,
     time::Duration,
 };
 
@@ -63,7 +88,56 @@ pub struct Config {
     /// The applications configuration list.
     ///
     /// This list defines what applications to start and how to start them.
+    #[serde(default)]
     pub applications: Vec<Application>,
+
+    /// Fragments whose applications to include in the project.
+    ///
+    /// The applications of the fragments are started after the
+    /// [`applications`][field-Config-applications], in the order the fragments are included. The
+    /// [`variables`][field-Config-variables] of the project are interpolated in them as well.
+    ///
+    /// [field-Config-applications]: #structfield.applications
+    /// [field-Config-variables]: #structfield.variables
+    #[serde(default)]
+    pub include: Vec<Include>,
+
+    /// Additional workspaces to start the project on, each with its own layout and applications.
+    ///
+    /// These are started in order, after the layout and applications of the general section.
+    #[serde(default)]
+    pub workspaces: Vec<Workspace>,
+
+    /// The variables of the project, which can be used as `${name}` throughout the configuration.
+    ///
+    /// See [`Variable`](struct.Variable.html).
+    #[serde(default)]
+    pub variables: BTreeMap<String, Variable>,
+}
+
+/// A variable of the project, declared in the `[variables]` table.
+///
+/// Variables can be used in every string of the project configuration and the fragments it includes
+/// as `${name}`. Their value is either given when starting the project (e.g. `i3nator start --set
+/// name=value`), or taken from the default.
+///
+/// # Example
+///
+/// A variable can be declared either by its default value, or by a map with an optional default
+/// and description. Variables without a default have to be set when starting the project.
+///
+/// ```toml
+/// [variables]
+/// branch = "master"
+/// repository = { description = "path to the repository to work on" }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Variable {
+    /// The value to use if the variable is not set when starting the project.
+    pub default: Option<String>,
+
+    /// A description of the variable.
+    pub description: Option<String>,
 }
 
 /// The general configuration section.
@@ -72,15 +146,49 @@ pub struct Config {
 #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct General {
+    /// Another managed project this project is based on.
+    ///
+    /// See [`Extends`](struct.Extends.html).
+    #[serde(default)]
+    pub extends: Option<Extends>,
+
     /// The working directory defines in which directory-context the applications should be
     /// launched in.
-    #[serde(default, deserialize_with = "deserialize_opt_pathbuf_with_tilde")]
+    #[serde(default, deserialize_with = "deserialize_opt_expanded_pathbuf")]
     pub working_directory: Option<PathBuf>,
 
+    /// Files in dotenv syntax (i.e. lines of `KEY=VALUE`) to load environment variables for all
+    /// applications from.
+    ///
+    /// Relative paths are resolved against the [`working_directory`][field-General-working_directory].
+    /// Variables from later files take precedence, as do variables defined in
+    /// [`env`][field-General-env].
+    ///
+    /// [field-General-env]: #structfield.env
+    /// [field-General-working_directory]: #structfield.working_directory
+    #[serde(default, deserialize_with = "deserialize_expanded_pathbufs")]
+    pub env_file: Vec<PathBuf>,
+
+    /// Environment variables to set for all applications.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Environment variables to remove from the environment of all applications.
+    #[serde(default)]
+    pub unset_env: Vec<String>,
+
     /// If the workspace is `Some`, `i3` will be instructed to open the layout on the specified
     /// workspace. If it is `None`, `i3` will use the currently focused workspace.
     pub workspace: Option<String>,
 
+    /// The outputs (i.e. monitors) to move the workspace to, in order of preference.
+    ///
+    /// See [`Workspace::output`][field-Workspace-output].
+    ///
+    /// [field-Workspace-output]: struct.Workspace.html#structfield.output
+    #[serde(default, deserialize_with = "deserialize_outputs")]
+    pub output: Vec<String>,
+
     /// The layout to append to a workspace.
     ///
     /// This should either be:
@@ -90,9 +198,113 @@ pub struct General {
     ///
     /// Either one will be passed to [`append_layout`][append-layout].
     ///
+    /// This can be omitted if the project only uses [`workspaces`][field-Config-workspaces].
+    ///
     /// [append-layout]: https://i3wm.org/docs/layout-saving.html#_append_layout_command
+    /// [field-Config-workspaces]: struct.Config.html#structfield.workspaces
+    #[serde(default, deserialize_with = "deserialize_opt_layout")]
+    pub layout: Option<Layout>,
+
+    /// Commands to run before the layouts are appended and the applications are started.
+    ///
+    /// If any of the commands fails, the project is not started.
+    #[serde(default, deserialize_with = "deserialize_opt_hooks")]
+    pub on_start: Option<Hooks>,
+
+    /// Commands to run after all applications have been started.
+    #[serde(default, deserialize_with = "deserialize_opt_hooks")]
+    pub after_start: Option<Hooks>,
+
+    /// Commands to run after the project has been stopped.
+    #[serde(default, deserialize_with = "deserialize_opt_hooks")]
+    pub on_stop: Option<Hooks>,
+}
+
+/// A project another project extends, as specified by
+/// [`General::extends`][field-General-extends].
+///
+/// The configuration of the extending project is merged into the configuration of the extended
+/// project: the options of the `general` section (and the `variables`) are merged, with the
+/// extending project taking precedence, while the `include`s and `workspaces` are appended to the
+/// ones of the extended project. The `applications` are either appended to or replace the ones of
+/// the extended project. Extended projects can in turn extend other projects.
+///
+/// # Example
+///
+/// A project can be extended either by its name, appending the applications, or by a map which
+/// also specifies how the applications are inherited.
+///
+/// ```toml
+/// [general]
+/// extends = "base"
+/// # or
+/// extends = { name = "base", applications = "replace" }
+/// ```
+///
+/// [field-General-extends]: struct.General.html#structfield.extends
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extends {
+    /// The name of the managed project to extend.
+    pub name: String,
+
+    /// How the `applications` of the extended project are inherited. Its `include`s and
+    /// `workspaces` are always appended to.
+    ///
+    /// If not specified, [`Inheritance::Append`][variant-Inheritance-Append] will be used by
+    /// default.
+    ///
+    /// [variant-Inheritance-Append]: enum.Inheritance.html#variant.Append
+    pub applications: Inheritance,
+}
+
+/// Defines how the `applications` of an extended project are inherited.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Inheritance {
+    /// Start the applications of the extending project after the ones of the extended project.
+    Append,
+
+    /// Only start the applications of the extending project, ignoring the ones of the extended
+    /// project.
+    Replace,
+}
+
+/// A workspace configuration.
+///
+/// This allows a project to spread across multiple workspaces, with every workspace getting its
+/// own layout and applications.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Workspace {
+    /// The name of the workspace to open the layout on.
+    pub workspace: String,
+
+    /// The outputs (i.e. monitors) to move the workspace to, in order of preference.
+    ///
+    /// The workspace is moved to the first output that is available, where `primary` refers to
+    /// the primary output. The outputs can either be specified as a string of whitespace separated
+    /// output names or as a sequence of output names. The following are equivalent:
+    ///
+    /// ```toml
+    /// output = "DP-1 HDMI-1 primary"
+    /// output = ["DP-1", "HDMI-1", "primary"]
+    /// ```
+    ///
+    /// If none of the outputs is available, the workspace stays where it is.
+    #[serde(default, deserialize_with = "deserialize_outputs")]
+    pub output: Vec<String>,
+
+    /// The layout to append to the workspace.
+    ///
+    /// See [`General::layout`][field-General-layout] for the supported formats.
+    ///
+    /// [field-General-layout]: struct.General.html#structfield.layout
     #[serde(deserialize_with = "deserialize_layout")]
     pub layout: Layout,
+
+    /// The applications to start on the workspace.
+    #[serde(default)]
+    pub applications: Vec<Application>,
 }
 
 /// This holds the layout, in multiple formats.
@@ -109,6 +321,54 @@ pub enum Layout {
     Path(PathBuf),
 }
 
+/// A fragment to include in a project.
+///
+/// # Example
+///
+/// ```toml
+/// [[include]]
+/// path = "htop"
+///
+/// [[include]]
+/// path = "~/.config/i3nator/fragments/htop.toml"
+/// ```
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Include {
+    /// The fragment to include, either the name of a managed fragment or the path to a fragment.
+    ///
+    /// Relative paths are resolved against the directory containing the project.
+    #[serde(deserialize_with = "deserialize_fragment")]
+    pub path: Fragment,
+}
+
+/// This holds the fragment to include, in multiple formats.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Fragment {
+    /// The name of a managed fragment.
+    Managed(String),
+
+    /// The fragment is provided as a path.
+    Path(PathBuf),
+}
+
+/// The configuration of a fragment, i.e. applications that can be included in projects.
+///
+/// # Example
+///
+/// ```toml
+/// [[applications]]
+/// command = "urxvt -e htop"
+/// ```
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FragmentConfig {
+    /// The applications of the fragment.
+    #[serde(default)]
+    pub applications: Vec<Application>,
+}
+
 /// The applications configuration.
 ///
 /// This configuration defines how to start an applications and what potential commands to execute
@@ -116,6 +376,17 @@ pub enum Layout {
 #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct Application {
+    /// A name to refer to the application by, e.g. in [`depends_on`][field-depends_on] or when
+    /// starting only some of the applications of a project.
+    ///
+    /// If not specified, the file name of the program is used, see
+    /// [`Application::name`][fn-Application-name].
+    ///
+    /// [field-depends_on]: #structfield.depends_on
+    /// [fn-Application-name]: #method.name
+    #[serde(default)]
+    pub name: Option<String>,
+
     /// The command used for starting an application.
     ///
     /// See [`ApplicationCommand`](struct.ApplicationCommand.html).
@@ -128,12 +399,136 @@ pub struct Application {
     /// This overrides [`general.working_directory`][general-working_directory].
     ///
     /// [general-working_directory]: struct.General.html#structfield.working_directory
-    #[serde(default, deserialize_with = "deserialize_opt_pathbuf_with_tilde")]
+    #[serde(default, deserialize_with = "deserialize_opt_expanded_pathbuf")]
     pub working_directory: Option<PathBuf>,
 
+    /// Files in dotenv syntax to load environment variables for the application from.
+    ///
+    /// These take precedence over the variables defined in the general section. Relative paths
+    /// are resolved against the working directory of the application. See
+    /// [`general.env_file`][general-env_file].
+    ///
+    /// [general-env_file]: struct.General.html#structfield.env_file
+    #[serde(default, deserialize_with = "deserialize_expanded_pathbufs")]
+    pub env_file: Vec<PathBuf>,
+
+    /// Environment variables to set for the application.
+    ///
+    /// These are added to the variables defined in [`general.env`][general-env], overriding
+    /// variables with the same name.
+    ///
+    /// [general-env]: struct.General.html#structfield.env
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Environment variables to remove from the environment of the application.
+    ///
+    /// This also removes variables defined in [`general.env`][general-env].
+    ///
+    /// [general-env]: struct.General.html#structfield.env
+    #[serde(default)]
+    pub unset_env: Vec<String>,
+
     /// Commands to execute or keys to simulate after application startup.
     #[serde(default, deserialize_with = "deserialize_opt_exec")]
     pub exec: Option<Exec>,
+
+    /// Specify a timeout for the application to open its window.
+    ///
+    /// When starting a project, i3nator waits for applications to open a window (see
+    /// [`wait_for_window`][field-wait_for_window]), either being swallowed by a placeholder of the
+    /// layout or originating from the started process, before starting the next application.
+    /// Applications that don't open a window within this timeout are reported.
+    ///
+    /// Set this to `0` to never wait for the window.
+    ///
+    /// [field-wait_for_window]: #structfield.wait_for_window
+    #[serde(default = "default_timeout", deserialize_with = "deserialize_duration")]
+    pub window_timeout: Duration,
+
+    /// Run the command through a shell with `-c`, such that pipes, `&&`, redirections and
+    /// globbing can be used.
+    ///
+    /// This can either be `true` to use `/bin/sh`, or the shell to use, e.g. `"/bin/zsh"`.
+    #[serde(default, deserialize_with = "deserialize_opt_shell")]
+    pub shell: Option<PathBuf>,
+
+    /// Names of applications which have to be started and ready before this application is
+    /// started.
+    ///
+    /// Applications are started in the order they are defined in, unless they have to wait for
+    /// their dependencies. Dependencies have to be defined on the same workspace or on one that is
+    /// started before.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+
+    /// Whether to wait for the application to open its window before the next application is
+    /// started.
+    ///
+    /// By default, i3nator waits for the windows of all applications, such that applications
+    /// which don't open one are reported. Setting this to `false` is equivalent to a
+    /// [`window_timeout`][field-window_timeout] of `0`, the [`exec`][field-exec] commands of the
+    /// application can't be input then.
+    ///
+    /// [field-exec]: #structfield.exec
+    /// [field-window_timeout]: #structfield.window_timeout
+    #[serde(default = "default_wait_for_window")]
+    pub wait_for_window: bool,
+
+    /// An address the application has to accept TCP connections on before the next application
+    /// is started.
+    ///
+    /// This can either be a port on `localhost` or an address like `"127.0.0.1:8080"`.
+    #[serde(default, deserialize_with = "deserialize_opt_address")]
+    pub wait_for_port: Option<String>,
+
+    /// A file that has to exist before the next application is started.
+    ///
+    /// Relative paths are resolved against the working directory of the application.
+    #[serde(default, deserialize_with = "deserialize_opt_expanded_pathbuf")]
+    pub wait_for_file: Option<PathBuf>,
+
+    /// Specify a timeout for [`wait_for_port`][field-wait_for_port] and
+    /// [`wait_for_file`][field-wait_for_file], defaults to 30 seconds.
+    ///
+    /// Applications that are not ready within this timeout are reported, and the next
+    /// application is started regardless.
+    ///
+    /// [field-wait_for_file]: #structfield.wait_for_file
+    /// [field-wait_for_port]: #structfield.wait_for_port
+    #[serde(
+        default = "default_ready_timeout",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub ready_timeout: Duration,
+
+    /// Time to wait after the application is ready before the next application is started.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub delay: Duration,
+}
+
+impl Application {
+    /// Returns the name of the application, falling back to the file name of its program if no
+    /// [`name`][field-name] was specified.
+    ///
+    /// [field-name]: #structfield.name
+    pub fn name(&self) -> &str {
+        match self.name {
+            Some(ref name) => name,
+            None => Path::new(&self.command.program)
+                .file_name()
+                .and_then(OsStr::to_str)
+                .unwrap_or(&self.command.program),
+        }
+    }
+
+    /// Returns whether i3nator waits for the application to open its window when starting the
+    /// project, as described for [`wait_for_window`][field-wait_for_window].
+    ///
+    /// [field-wait_for_window]: #structfield.wait_for_window
+    pub fn waits_for_window(&self) -> bool {
+        self.wait_for_window && self.window_timeout > Duration::from_secs(0)
+    }
 }
 
 /// The command used for starting an application.
@@ -167,7 +562,11 @@ pub struct Application {
 /// ```
 ///
 /// A string will be split up into separate args, honoring single- and double-quoted elements.
-#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+/// Commands that are run through a [`shell`][field-Application-shell] are passed to the shell as
+/// they were specified instead.
+///
+/// [field-Application-shell]: struct.Application.html#structfield.shell
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct ApplicationCommand {
     /// The executable to start.
     pub program: String,
@@ -175,11 +574,73 @@ pub struct ApplicationCommand {
     /// A list of arguments to pass to the executable.
     #[serde(default)]
     pub args: Vec<String>,
+
+    /// The command as it was specified, if it was specified as a string.
+    ///
+    /// This is not taken into account when comparing commands, i.e. commands are equal if they
+    /// start the same program with the same arguments.
+    #[serde(skip)]
+    pub commandline: Option<String>,
+}
+
+impl PartialEq for ApplicationCommand {
+    fn eq(&self, other: &Self) -> bool {
+        self.program == other.program && self.args == other.args
+    }
+}
+
+impl Eq for ApplicationCommand {}
+
+/// Commands to run when a project is started or stopped, see e.g.
+/// [`General::on_start`][field-General-on_start].
+///
+/// The commands are run one after another, in the working directory and with the environment
+/// configured in the general section.
+///
+/// # Example
+///
+/// This struct can be deserialized (from TOML) either from a string, a sequence of strings or a
+/// map, which also allows specifying the timeout:
+///
+/// ```toml
+/// on_start = "git fetch"
+/// on_start = ["git fetch", "docker compose up -d"]
+/// on_start = { commands = ["git fetch", "docker compose up -d"], timeout = 120 }
+/// ```
+///
+/// [field-General-on_start]: struct.General.html#structfield.on_start
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    /// List of commands to run, every one of which is split up like
+    /// [`Application::command`][field-Application-command].
+    ///
+    /// [field-Application-command]: struct.Application.html#structfield.command
+    #[serde(deserialize_with = "deserialize_application_commands")]
+    pub commands: Vec<ApplicationCommand>,
+
+    /// Run the commands through a shell, see [`Application::shell`][field-Application-shell].
+    ///
+    /// [field-Application-shell]: struct.Application.html#structfield.shell
+    #[serde(default, deserialize_with = "deserialize_opt_shell")]
+    pub shell: Option<PathBuf>,
+
+    /// Specify a timeout within which every command has to finish. Commands that don't finish in
+    /// time are killed and count as failed.
+    ///
+    /// If not specified, a timeout of 60 seconds is used.
+    #[serde(
+        default = "default_hook_timeout",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub timeout: Duration,
 }
 
 /// Commands to execute or keys to simulate after application startup.
 ///
-/// `xdotool` is used to simulate text or keys to input.
+/// The text or keys are input by the configured [`backend`][field-Exec-backend].
+///
+/// [field-Exec-backend]: #structfield.backend
 #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Exec {
     /// List of text or keys to input into the application.
@@ -189,8 +650,19 @@ pub struct Exec {
     ///
     /// [xdotool-keyboard]:
     ///   https://github.com/jordansissel/xdotool/blob/master/xdotool.pod#keyboard-commands
+    #[serde(default)]
     pub commands: Vec<String>,
 
+    /// Steps to input into the application, in order.
+    ///
+    /// Other than `commands`, which all share a single [`exec_type`][field-Exec-exec_type], steps
+    /// can mix text, key presses and waiting. If steps are given, they are input after the
+    /// `commands`.
+    ///
+    /// [field-Exec-exec_type]: #structfield.exec_type
+    #[serde(default)]
+    pub steps: Vec<ExecStep>,
+
     /// Defines how the commands above should be interpreted.
     ///
     /// If not specified, [`ExecType::Text`][variant-ExecType-Text] will be used by default.
@@ -199,26 +671,188 @@ pub struct Exec {
     #[serde(default = "default_exec_type")]
     pub exec_type: ExecType,
 
+    /// Defines how the commands are input into the application.
+    ///
+    /// If not specified, [`ExecBackend::X11`][variant-ExecBackend-X11] will be used by default.
+    ///
+    /// [variant-ExecBackend-X11]: enum.ExecBackend.html#variant.X11
+    #[serde(default = "default_exec_backend")]
+    pub backend: ExecBackend,
+
+    /// The target of the [`backend`][field-Exec-backend], required for
+    /// [`ExecBackend::Tmux`][variant-ExecBackend-Tmux],
+    /// [`ExecBackend::Kitty`][variant-ExecBackend-Kitty] and
+    /// [`ExecBackend::Wezterm`][variant-ExecBackend-Wezterm].
+    ///
+    /// [field-Exec-backend]: #structfield.backend
+    /// [variant-ExecBackend-Kitty]: enum.ExecBackend.html#variant.Kitty
+    /// [variant-ExecBackend-Tmux]: enum.ExecBackend.html#variant.Tmux
+    /// [variant-ExecBackend-Wezterm]: enum.ExecBackend.html#variant.Wezterm
+    #[serde(default)]
+    pub target: Option<String>,
+
+    /// Defines when the commands are input, e.g. once the application is ready to receive them.
+    ///
+    /// If not specified, the commands are input as soon as the window of the application is
+    /// found.
+    #[serde(default)]
+    pub wait_for: WaitFor,
+
+    /// Defines how the commands are pasted, if [`ExecType::Paste`][variant-ExecType-Paste] is
+    /// used.
+    ///
+    /// [variant-ExecType-Paste]: enum.ExecType.html#variant.Paste
+    #[serde(default)]
+    pub paste: Paste,
+
     /// Specify a timeout after which a command has to be succesfully input into the application.
     ///
-    /// The input of commands is done with `xdotool --sync`, that is `xdotool` will block until the
-    /// required application starts up. `xdotool` might fail to find a started application, if that
+    /// Before commands are input, the backend waits for the application to open its window, which
+    /// is found using the `_NET_WM_PID` property. An application might not be found, if that
     /// application does not behave well within the X11 standards.
     ///
-    /// In this case, `xdotool` would block indefinitely. This timeout will kill the `xdotool`
-    /// process if it does not exit (successfully or unsuccessfully).
+    /// In this case, the backend would wait indefinitely. This timeout will stop waiting (and kill
+    /// the `xdotool` process, if it is used) once it is exceeded.
     #[serde(default = "default_timeout", deserialize_with = "deserialize_duration")]
     pub timeout: Duration,
 }
 
+/// Conditions to wait for before and while inputting the commands of [`Exec`][struct-Exec].
+///
+/// [struct-Exec]: struct.Exec.html
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct WaitFor {
+    /// Regular expression the title of the window has to match before the commands are input,
+    /// e.g. `"\\$ $"` to wait for a shell prompt in terminals showing it in their title.
+    ///
+    /// The title is observed through i3, which requires i3nator to wait for the window of the
+    /// application (see [`Application::wait_for_window`][field-Application-wait_for_window]). If
+    /// the title doesn't match within [`Exec::timeout`][field-Exec-timeout], the commands are not
+    /// input.
+    ///
+    /// [field-Application-wait_for_window]: struct.Application.html#structfield.wait_for_window
+    /// [field-Exec-timeout]: struct.Exec.html#structfield.timeout
+    #[serde(default, deserialize_with = "deserialize_opt_regex")]
+    pub title: Option<String>,
+
+    /// Time to wait before the first command is input, in seconds.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub delay: Duration,
+
+    /// Time to wait between two commands, in seconds. Between [`Exec::steps`][field-Exec-steps],
+    /// this only applies to consecutive steps inputting something, not to `sleep` or `wait_title`
+    /// steps.
+    ///
+    /// [field-Exec-steps]: struct.Exec.html#structfield.steps
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub between: Duration,
+}
+
+fn default_exec_backend() -> ExecBackend {
+    ExecBackend::X11
+}
+
 fn default_exec_type() -> ExecType {
     ExecType::Text
 }
 
+fn default_hook_timeout() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_inheritance() -> Inheritance {
+    Inheritance::Append
+}
+
+fn default_paste_keys() -> String {
+    "ctrl+shift+v".to_owned()
+}
+
+fn default_paste_selection() -> PasteSelection {
+    PasteSelection::Clipboard
+}
+
+fn default_ready_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
 fn default_timeout() -> Duration {
     Duration::from_secs(5)
 }
 
+fn default_wait_for_window() -> bool {
+    true
+}
+
+/// Defines how the commands in [`Exec`][struct-Exec] are input into the application.
+///
+/// [struct-Exec]: struct.Exec.html
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecBackend {
+    /// Focus the window of the application and simulate the key-presses using the XTEST
+    /// extension of the X server.
+    X11,
+
+    /// Use [`xdotool`][xdotool], which has to be installed, to send the key-presses to the window
+    /// of the application.
+    ///
+    /// [xdotool]: https://github.com/jordansissel/xdotool
+    Xdotool,
+
+    /// Send the text to a tmux pane using `tmux send-keys`. The pane is given by
+    /// [`Exec::target`][field-Exec-target] (e.g. the name of the session), which is waited for to
+    /// be created.
+    ///
+    /// [field-Exec-target]: struct.Exec.html#structfield.target
+    Tmux,
+
+    /// Send the text to kitty using its remote control (`kitty @ send-text`). kitty has to be
+    /// started with `--listen-on`, the address of which is given by
+    /// [`Exec::target`][field-Exec-target], e.g. `unix:/tmp/kitty-project`.
+    ///
+    /// [field-Exec-target]: struct.Exec.html#structfield.target
+    Kitty,
+
+    /// Send the text to a wezterm pane using `wezterm cli send-text`. The pane id is given by
+    /// [`Exec::target`][field-Exec-target], which is waited for to accept commands.
+    ///
+    /// [field-Exec-target]: struct.Exec.html#structfield.target
+    Wezterm,
+}
+
+/// A single step of [`Exec::steps`][field-Exec-steps].
+///
+/// Steps are specified as tables with a single key, e.g. `{ text = "ls" }` or `{ sleep = 1.5 }`.
+///
+/// [field-Exec-steps]: struct.Exec.html#structfield.steps
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum ExecStep {
+    /// Input the text, without a `Return`.
+    Text(String),
+
+    /// Paste the text, without a `Return`, see [`ExecType::Paste`][variant-ExecType-Paste].
+    ///
+    /// [variant-ExecType-Paste]: enum.ExecType.html#variant.Paste
+    Paste(String),
+
+    /// Press the keys one after another, in the format `xdotool` expects them (e.g. `ctrl+r`).
+    Keys(Vec<String>),
+
+    /// Wait for the given time, in seconds.
+    #[serde(deserialize_with = "deserialize_duration")]
+    Sleep(Duration),
+
+    /// Wait for the title of the window to match the regular expression, see
+    /// [`WaitFor::title`][field-WaitFor-title]. If it doesn't, the remaining steps are not input.
+    ///
+    /// [field-WaitFor-title]: struct.WaitFor.html#structfield.title
+    #[serde(deserialize_with = "deserialize_regex")]
+    WaitTitle(String),
+}
+
 /// Defines how the commands in [`Exec`][struct-Exec] should be interpreted.
 ///
 /// [struct-Exec]: struct.Exec.html
@@ -236,11 +870,254 @@ pub enum ExecType {
     ///
     /// This does not input any `Return`s.
     Keys,
+
+    /// Interpret the commands given as separate text-lines, pasting them in order with a `Return`
+    /// after each.
+    ///
+    /// Other than typing the text, this is fast for long commands and not affected by the
+    /// keyboard layout. See [`Exec::paste`][field-Exec-paste] for how the text is pasted.
+    ///
+    /// [field-Exec-paste]: struct.Exec.html#structfield.paste
+    Paste,
 }
 
-struct Phantom<T>(PhantomData<T>);
+/// Defines how the commands of [`Exec`][struct-Exec] are pasted, if
+/// [`ExecType::Paste`][variant-ExecType-Paste] is used.
+///
+/// With the backends inputting into the window, i3nator owns an X selection providing the text
+/// and presses the paste keys. Once the application requested the text, the previous text
+/// contents of the selection are restored by a detached process providing them until another
+/// application takes over the selection (like `xclip` does), or the selection is cleared if it
+/// wasn't owned by anyone. Other contents, like images, are not restored. The terminal backends paste the text directly and ignore these options.
+///
+/// [struct-Exec]: struct.Exec.html
+/// [variant-ExecType-Paste]: enum.ExecType.html#variant.Paste
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Paste {
+    /// The X selection to place the text in.
+    ///
+    /// If not specified, [`PasteSelection::Clipboard`][variant-PasteSelection-Clipboard] will be
+    /// used by default.
+    ///
+    /// [variant-PasteSelection-Clipboard]: enum.PasteSelection.html#variant.Clipboard
+    #[serde(default = "default_paste_selection")]
+    pub selection: PasteSelection,
 
-fn deserialize_application_command<'de, D>(deserializer: D) -> Result<ApplicationCommand, D::Error>
+    /// The keys to press to paste the text, in the format `xdotool` expects them.
+    ///
+    /// If not specified, `ctrl+shift+v` will be used by default, which is what most terminals
+    /// use. Most applications paste the primary selection with `shift+Insert`.
+    #[serde(default = "default_paste_keys")]
+    pub keys: String,
+}
+
+impl Default for Paste {
+    fn default() -> Self {
+        Paste {
+            selection: default_paste_selection(),
+            keys: default_paste_keys(),
+        }
+    }
+}
+
+/// The X selection text is pasted from, see [`Paste`][struct-Paste].
+///
+/// [struct-Paste]: struct.Paste.html
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteSelection {
+    /// The clipboard, i.e. the selection copied to explicitly.
+    Clipboard,
+
+    /// The primary selection, i.e. the selected text.
+    Primary,
+}
+
+struct Phantom<T>(PhantomData<T>);
+
+impl<'de> Deserialize<'de> for Variable {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Definition {
+            default: Option<String>,
+            description: Option<String>,
+        }
+
+        impl<'de> de::Visitor<'de> for Phantom<Variable> {
+            type Value = Variable;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("string or map")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Variable {
+                    default: Some(value.to_owned()),
+                    description: None,
+                })
+            }
+
+            fn visit_map<M>(self, visitor: M) -> Result<Self::Value, M::Error>
+            where
+                M: de::MapAccess<'de>,
+            {
+                let definition: Definition =
+                    de::Deserialize::deserialize(de::value::MapAccessDeserializer::new(visitor))?;
+                Ok(Variable {
+                    default: definition.default,
+                    description: definition.description,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(Phantom::<Variable>(PhantomData))
+    }
+}
+
+/// An element of the sequence form of [`Exec`][struct-Exec], i.e. either a command or a step.
+///
+/// [struct-Exec]: struct.Exec.html
+enum ExecElement {
+    Command(String),
+    Step(ExecStep),
+}
+
+impl<'de> Deserialize<'de> for ExecElement {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        impl<'de> de::Visitor<'de> for Phantom<ExecElement> {
+            type Value = ExecElement;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("string or map")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(ExecElement::Command(value.to_owned()))
+            }
+
+            fn visit_map<M>(self, mut visitor: M) -> Result<Self::Value, M::Error>
+            where
+                M: de::MapAccess<'de>,
+            {
+                // Only the first entry is used to determine the step, every other would be ignored
+                let step = de::Deserialize::deserialize(de::value::MapAccessDeserializer::new(
+                    &mut visitor,
+                ))?;
+                if visitor.next_key::<de::IgnoredAny>()?.is_some() {
+                    return Err(de::Error::custom("a step can only contain a single key"));
+                }
+
+                Ok(ExecElement::Step(step))
+            }
+        }
+
+        deserializer.deserialize_any(Phantom::<ExecElement>(PhantomData))
+    }
+}
+
+impl<'de> Deserialize<'de> for Extends {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Definition {
+            name: String,
+            #[serde(default = "default_inheritance")]
+            applications: Inheritance,
+        }
+
+        impl<'de> de::Visitor<'de> for Phantom<Extends> {
+            type Value = Extends;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("string or map")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Extends {
+                    name: value.to_owned(),
+                    applications: default_inheritance(),
+                })
+            }
+
+            fn visit_map<M>(self, visitor: M) -> Result<Self::Value, M::Error>
+            where
+                M: de::MapAccess<'de>,
+            {
+                let definition: Definition =
+                    de::Deserialize::deserialize(de::value::MapAccessDeserializer::new(visitor))?;
+                Ok(Extends {
+                    name: definition.name,
+                    applications: definition.applications,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(Phantom::<Extends>(PhantomData))
+    }
+}
+
+fn deserialize_opt_address<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    impl<'de> de::Visitor<'de> for Phantom<Option<String>> {
+        type Value = Option<String>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("port or address")
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            if (1..=65535).contains(&value) {
+                Ok(Some(format!("localhost:{}", value)))
+            } else {
+                Err(E::custom(format!("invalid port {}", value)))
+            }
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            match value.rsplit_once(':') {
+                Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                    Ok(Some(value.to_owned()))
+                }
+                _ => Err(E::custom(format!(
+                    "invalid address '{}', expected `host:port`",
+                    value
+                ))),
+            }
+        }
+    }
+
+    deserializer.deserialize_any(Phantom::<Option<String>>(PhantomData))
+}
+
+fn deserialize_application_command<'de, D>(deserializer: D) -> Result<ApplicationCommand, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -263,6 +1140,7 @@ where
                         Ok(ApplicationCommand {
                             program: v.remove(0).to_owned(),
                             args: v.into_iter().map(str::to_owned).collect::<Vec<_>>(),
+                            commandline: Some(value.to_owned()),
                         })
                     }
                 }
@@ -282,6 +1160,7 @@ where
                 Ok(ApplicationCommand {
                     program: v.remove(0),
                     args: v,
+                    commandline: None,
                 })
             }
         }
@@ -297,6 +1176,77 @@ where
     deserializer.deserialize_any(Phantom::<ApplicationCommand>(PhantomData))
 }
 
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let regex = String::deserialize(deserializer)?;
+    Regex::new(&regex).map_err(de::Error::custom)?;
+    Ok(regex)
+}
+
+fn deserialize_opt_regex<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_regex(deserializer).map(Some)
+}
+
+fn deserialize_opt_shell<'de, D>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    impl<'de> de::Visitor<'de> for Phantom<Option<PathBuf>> {
+        type Value = Option<PathBuf>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("boolean or string")
+        }
+
+        fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(if value {
+                Some(PathBuf::from("/bin/sh"))
+            } else {
+                None
+            })
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            expand(value).map(Some).map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_any(Phantom::<Option<PathBuf>>(PhantomData))
+}
+
+fn deserialize_application_commands<'de, D>(
+    deserializer: D,
+) -> Result<Vec<ApplicationCommand>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Command(
+        #[serde(deserialize_with = "deserialize_application_command")] ApplicationCommand,
+    );
+
+    let commands: Vec<Command> = de::Deserialize::deserialize(deserializer)?;
+    Ok(commands
+        .into_iter()
+        .map(|Command(command)| command)
+        .collect())
+}
+
+/// The longest duration that can be configured, one day. Anything longer is most likely a
+/// mistake.
+const MAX_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
 fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
 where
     D: Deserializer<'de>,
@@ -305,21 +1255,69 @@ where
         type Value = Duration;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("integer or map")
+            write!(
+                formatter,
+                "integer, float or map of at most {} seconds",
+                MAX_DURATION.as_secs()
+            )
         }
 
         fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
         where
             E: de::Error,
         {
-            Ok(Duration::from_secs(value as u64))
+            if value >= 0 && value as u64 <= MAX_DURATION.as_secs() {
+                Ok(Duration::from_secs(value as u64))
+            } else {
+                Err(de::Error::invalid_value(
+                    de::Unexpected::Signed(value),
+                    &self,
+                ))
+            }
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            if value <= MAX_DURATION.as_secs() {
+                Ok(Duration::from_secs(value))
+            } else {
+                Err(de::Error::invalid_value(
+                    de::Unexpected::Unsigned(value),
+                    &self,
+                ))
+            }
+        }
+
+        fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            if value >= 0.0 && value <= MAX_DURATION.as_secs_f64() {
+                Ok(Duration::from_secs_f64(value))
+            } else {
+                Err(de::Error::invalid_value(
+                    de::Unexpected::Float(value),
+                    &self,
+                ))
+            }
         }
 
         fn visit_map<M>(self, visitor: M) -> Result<Self::Value, M::Error>
         where
             M: de::MapAccess<'de>,
         {
-            de::Deserialize::deserialize(de::value::MapAccessDeserializer::new(visitor))
+            let duration: Duration =
+                de::Deserialize::deserialize(de::value::MapAccessDeserializer::new(visitor))?;
+            if duration <= MAX_DURATION {
+                Ok(duration)
+            } else {
+                Err(de::Error::invalid_value(
+                    de::Unexpected::Other("longer duration"),
+                    &self,
+                ))
+            }
         }
     }
 
@@ -343,24 +1341,43 @@ where
         {
             Ok(Exec {
                 commands: vec![value.to_owned()],
+                steps: vec![],
                 exec_type: default_exec_type(),
+                backend: default_exec_backend(),
+                target: None,
+                wait_for: WaitFor::default(),
+                paste: Paste::default(),
                 timeout: default_timeout(),
             })
         }
 
-        fn visit_seq<S>(self, visitor: S) -> Result<Self::Value, S::Error>
+        fn visit_seq<S>(self, mut visitor: S) -> Result<Self::Value, S::Error>
         where
             S: de::SeqAccess<'de>,
         {
-            let v: Vec<String> =
-                de::Deserialize::deserialize(de::value::SeqAccessDeserializer::new(visitor))?;
+            // The sequence either contains commands or steps
+            let mut commands = vec![];
+            let mut steps = vec![];
+            while let Some(element) = visitor.next_element::<ExecElement>()? {
+                match element {
+                    ExecElement::Command(command) => commands.push(command),
+                    ExecElement::Step(step) => steps.push(step),
+                }
+            }
 
-            if v.is_empty() {
+            if commands.is_empty() && steps.is_empty() {
                 Err(de::Error::custom("commands can not be empty"))
+            } else if !commands.is_empty() && !steps.is_empty() {
+                Err(de::Error::custom("commands and steps can not be mixed"))
             } else {
                 Ok(Exec {
-                    commands: v,
+                    commands,
+                    steps,
                     exec_type: default_exec_type(),
+                    backend: default_exec_backend(),
+                    target: None,
+                    wait_for: WaitFor::default(),
+                    paste: Paste::default(),
                     timeout: default_timeout(),
                 })
             }
@@ -370,7 +1387,13 @@ where
         where
             M: de::MapAccess<'de>,
         {
-            de::Deserialize::deserialize(de::value::MapAccessDeserializer::new(visitor))
+            let exec: Exec =
+                de::Deserialize::deserialize(de::value::MapAccessDeserializer::new(visitor))?;
+            if exec.commands.is_empty() && exec.steps.is_empty() {
+                Err(de::Error::custom("commands can not be empty"))
+            } else {
+                Ok(exec)
+            }
         }
     }
 
@@ -384,6 +1407,61 @@ where
     deserialize_exec(deserializer).map(Some)
 }
 
+fn deserialize_hooks<'de, D>(deserializer: D) -> Result<Hooks, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    impl<'de> de::Visitor<'de> for Phantom<Hooks> {
+        type Value = Hooks;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("string, sequence of strings or map")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(Hooks {
+                commands: vec![deserialize_application_command(
+                    de::IntoDeserializer::into_deserializer(value),
+                )?],
+                timeout: default_hook_timeout(),
+                shell: None,
+            })
+        }
+
+        fn visit_seq<S>(self, visitor: S) -> Result<Self::Value, S::Error>
+        where
+            S: de::SeqAccess<'de>,
+        {
+            Ok(Hooks {
+                commands: deserialize_application_commands(de::value::SeqAccessDeserializer::new(
+                    visitor,
+                ))?,
+                timeout: default_hook_timeout(),
+                shell: None,
+            })
+        }
+
+        fn visit_map<M>(self, visitor: M) -> Result<Self::Value, M::Error>
+        where
+            M: de::MapAccess<'de>,
+        {
+            de::Deserialize::deserialize(de::value::MapAccessDeserializer::new(visitor))
+        }
+    }
+
+    deserializer.deserialize_any(Phantom::<Hooks>(PhantomData))
+}
+
+fn deserialize_opt_hooks<'de, D>(deserializer: D) -> Result<Option<Hooks>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_hooks(deserializer).map(Some)
+}
+
 fn deserialize_layout<'de, D>(deserializer: D) -> Result<Layout, D::Error>
 where
     D: Deserializer<'de>,
@@ -404,7 +1482,7 @@ where
             } else if ManagedLayout::open(value).is_ok() {
                 Ok(Layout::Managed(value.to_owned()))
             } else {
-                Ok(Layout::Path(tilde(value).into_owned()))
+                expand(value).map(Layout::Path).map_err(E::custom)
             }
         }
     }
@@ -412,63 +1490,252 @@ where
     deserializer.deserialize_any(Phantom::<Layout>(PhantomData))
 }
 
-fn deserialize_pathbuf_with_tilde<'de, D>(deserializer: D) -> Result<PathBuf, D::Error>
+fn deserialize_opt_layout<'de, D>(deserializer: D) -> Result<Option<Layout>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_layout(deserializer).map(Some)
+}
+
+fn deserialize_fragment<'de, D>(deserializer: D) -> Result<Fragment, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: String = de::Deserialize::deserialize(deserializer)?;
+    if ManagedFragment::open(&value).is_ok() {
+        Ok(Fragment::Managed(value))
+    } else {
+        expand(&value)
+            .map(Fragment::Path)
+            .map_err(de::Error::custom)
+    }
+}
+
+fn deserialize_outputs<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    impl<'de> de::Visitor<'de> for Phantom<Vec<String>> {
+        type Value = Vec<String>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("string or sequence of strings")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.split_whitespace().map(str::to_owned).collect())
+        }
+
+        fn visit_seq<S>(self, visitor: S) -> Result<Self::Value, S::Error>
+        where
+            S: de::SeqAccess<'de>,
+        {
+            de::Deserialize::deserialize(de::value::SeqAccessDeserializer::new(visitor))
+        }
+    }
+
+    deserializer.deserialize_any(Phantom::<Vec<String>>(PhantomData))
+}
+
+fn deserialize_expanded_pathbuf<'de, D>(deserializer: D) -> Result<PathBuf, D::Error>
 where
     D: Deserializer<'de>,
 {
     let pathbuf: PathBuf = de::Deserialize::deserialize(deserializer)?;
-    Ok(tilde(&pathbuf).into_owned())
+    expand(&pathbuf).map_err(de::Error::custom)
+}
+
+fn deserialize_expanded_pathbufs<'de, D>(deserializer: D) -> Result<Vec<PathBuf>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    impl<'de> de::Visitor<'de> for Phantom<Vec<PathBuf>> {
+        type Value = Vec<PathBuf>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("string or sequence of strings")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(vec![expand(value).map_err(E::custom)?])
+        }
+
+        fn visit_seq<S>(self, visitor: S) -> Result<Self::Value, S::Error>
+        where
+            S: de::SeqAccess<'de>,
+        {
+            let pathbufs: Vec<PathBuf> =
+                de::Deserialize::deserialize(de::value::SeqAccessDeserializer::new(visitor))?;
+            pathbufs
+                .iter()
+                .map(|pathbuf| expand(pathbuf).map_err(de::Error::custom))
+                .collect()
+        }
+    }
+
+    deserializer.deserialize_any(Phantom::<Vec<PathBuf>>(PhantomData))
 }
 
-fn deserialize_opt_pathbuf_with_tilde<'de, D>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
+fn deserialize_opt_expanded_pathbuf<'de, D>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    deserialize_pathbuf_with_tilde(deserializer).map(Some)
+    deserialize_expanded_pathbuf(deserializer).map(Some)
 }
 
-/// Taken from crate "shellexpand", adapted to work with `Path` instead of `str`:
-///   https://github.com/netvl/shellexpand/blob/
-///     501c4fdd8275fea2e56e71a2659cd90d21d18565/src/lib.rs#L558-L639
-///
-/// (Linebreak in link to make rustfmt happy...)
+/// Expand a path the way a shell would, i.e. a leading `~` or `~user` is replaced by the
+/// respective home directory, and environment variables given as `$NAME` or `${NAME}` are replaced
+/// by their value.
 ///
-/// Dual-licensed under MIT/Apache 2.0
-/// Copyright (c) 2016 Vladimir Matveev
-#[doc(hidden)]
-fn tilde_with_context<SI: ?Sized, P, HD>(input: &SI, home_dir: HD) -> Cow<Path>
+/// A `$` that is not followed by a variable name is kept as is. Referencing a variable that is not
+/// set, a user that doesn't exist or a home directory that can't be determined is an error.
+fn expand_with_context<SI, HD, UD, EV>(
+    input: &SI,
+    home_dir: HD,
+    user_home_dir: UD,
+    env_var: EV,
+) -> Result<PathBuf, String>
 where
-    SI: AsRef<Path>,
-    P: AsRef<Path>,
-    HD: FnOnce() -> Option<P>,
+    SI: AsRef<Path> + ?Sized,
+    HD: FnOnce() -> Option<PathBuf>,
+    UD: FnOnce(&str) -> Option<PathBuf>,
+    EV: Fn(&str) -> Option<OsString>,
 {
-    let input_str = input.as_ref();
-    let bytes = input_str.as_os_str().as_bytes();
-    if bytes[0] == b'~' {
-        let input_after_tilde = &bytes[1..];
-        if input_after_tilde.is_empty() || input_after_tilde[0] == b'/' {
-            if let Some(hd) = home_dir() {
-                let mut s = OsString::new();
-                s.push(hd.as_ref());
-                s.push(OsStr::from_bytes(input_after_tilde));
-                PathBuf::from(s).into()
-            } else {
-                // home dir is not available
-                input_str.into()
-            }
+    let bytes = input.as_ref().as_os_str().as_bytes();
+    let mut expanded = OsString::new();
+
+    let mut rest = bytes;
+    if let Some(after_tilde) = bytes.strip_prefix(b"~") {
+        let end = after_tilde
+            .iter()
+            .position(|&byte| byte == b'/')
+            .unwrap_or(after_tilde.len());
+        let (user, after_user) = after_tilde.split_at(end);
+        if user.is_empty() {
+            let hd =
+                home_dir().ok_or_else(|| "home directory could not be determined".to_owned())?;
+            expanded.push(hd);
         } else {
-            // we cannot handle `~otheruser/` paths yet
-            input_str.into()
+            let user = String::from_utf8_lossy(user);
+            let hd =
+                user_home_dir(&user).ok_or_else(|| format!("user '{}' doesn't exist", user))?;
+            expanded.push(hd);
         }
-    } else {
-        // input doesn't start with tilde
-        input_str.into()
+        rest = after_user;
+    }
+
+    while let Some(dollar) = rest.iter().position(|&byte| byte == b'$') {
+        expanded.push(OsStr::from_bytes(&rest[..dollar]));
+        let after_dollar = &rest[dollar + 1..];
+
+        let (name, after_name) = if let Some(braced) = after_dollar.strip_prefix(b"{") {
+            let end = braced
+                .iter()
+                .position(|&byte| byte == b'}')
+                .ok_or_else(|| "unterminated variable reference '${'".to_owned())?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after_dollar
+                .iter()
+                .position(|&byte| !(byte.is_ascii_alphanumeric() || byte == b'_'))
+                .unwrap_or(after_dollar.len());
+            (&after_dollar[..end], &after_dollar[end..])
+        };
+
+        if name.is_empty() || name[0].is_ascii_digit() {
+            // Not a variable, keep the dollar sign as is
+            expanded.push("$");
+            rest = after_dollar;
+            continue;
+        }
+
+        let name = String::from_utf8_lossy(name);
+        let value =
+            env_var(&name).ok_or_else(|| format!("environment variable '{}' is not set", name))?;
+        expanded.push(value);
+        rest = after_name;
     }
+    expanded.push(OsStr::from_bytes(rest));
+
+    Ok(PathBuf::from(expanded))
 }
 
-fn tilde<SI: ?Sized>(input: &SI) -> Cow<Path>
+fn expand<SI>(input: &SI) -> Result<PathBuf, String>
 where
-    SI: AsRef<Path>,
+    SI: AsRef<Path> + ?Sized,
 {
-    tilde_with_context(input, dirs_next::home_dir)
+    expand_with_context(input, dirs_next::home_dir, user_home_dir, |name| {
+        env::var_os(name)
+    })
+}
+
+/// Look up the home directory of `user` in the user database, using `getpwnam_r(3)`.
+fn user_home_dir(user: &str) -> Option<PathBuf> {
+    let name = CString::new(user).ok()?;
+    let mut buffer = vec![0 as libc::c_char; 1024];
+    loop {
+        let mut passwd = mem::MaybeUninit::<libc::passwd>::uninit();
+        let mut result = ptr::null_mut();
+        let code = unsafe {
+            libc::getpwnam_r(
+                name.as_ptr(),
+                passwd.as_mut_ptr(),
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut result,
+            )
+        };
+
+        match code {
+            // The buffer is too small to hold the entry
+            libc::ERANGE => buffer.resize(buffer.len() * 2, 0),
+            0 if !result.is_null() => {
+                let home_dir = unsafe { CStr::from_ptr((*result).pw_dir) };
+                return Some(PathBuf::from(OsStr::from_bytes(home_dir.to_bytes())));
+            }
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_expansion_with_context() {
+        let expand = |input: &str| {
+            expand_with_context(
+                input,
+                || Some(PathBuf::from("/home/me")),
+                |user| match user {
+                    "alice" => Some(PathBuf::from("/home/alice")),
+                    _ => None,
+                },
+                |name| match name {
+                    "PROJECT" => Some("i3nator".into()),
+                    _ => None,
+                },
+            )
+        };
+
+        assert_eq!(expand("~/.env"), Ok(PathBuf::from("/home/me/.env")));
+        assert_eq!(expand("~alice"), Ok(PathBuf::from("/home/alice")));
+        assert_eq!(
+            expand("~alice/src/${PROJECT}/.env"),
+            Ok(PathBuf::from("/home/alice/src/i3nator/.env"))
+        );
+        // A tilde is only expanded at the start of the path
+        assert_eq!(expand("/src/~alice"), Ok(PathBuf::from("/src/~alice")));
+        assert_eq!(
+            expand("~bob/.env"),
+            Err("user 'bob' doesn't exist".to_owned())
+        );
+    }
 }
@@ -0,0 +1,219 @@
+// Copyright Pit Kleyersburg <pitkley@googlemail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified or distributed
+// except according to those terms.
+
+use crate::{
+    errors::*,
+    shlex,
+    types::{Application, ApplicationCommand, Config, Variable},
+};
+use std::collections::BTreeMap;
+use toml::Value;
+
+/// Determine the values of the variables declared in the `[variables]` table of the given project
+/// configuration.
+///
+/// Values in `overrides` take precedence over the defaults of the declared variables.
+pub fn resolve(
+    config: &Value,
+    overrides: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>> {
+    let declared = match config.get("variables") {
+        Some(variables) => variables.clone().try_into::<BTreeMap<String, Variable>>()?,
+        None => BTreeMap::new(),
+    };
+
+    if let Some(name) = overrides.keys().find(|name| !declared.contains_key(*name)) {
+        return Err(ErrorKind::UnknownVariable(name.to_owned()).into());
+    }
+
+    let mut values = BTreeMap::new();
+    for (name, variable) in declared {
+        let value = overrides
+            .get(&name)
+            .cloned()
+            .or(variable.default)
+            .ok_or_else(|| ErrorKind::UndefinedVariable(name.to_owned(), variable.description))?;
+        values.insert(name, value);
+    }
+
+    Ok(values)
+}
+
+/// Replace all occurrences of `${name}` in the strings of the given configuration with the value of
+/// the variable `name`, as determined by [`resolve`](fn.resolve.html).
+///
+/// Commands are left untouched, they are interpolated once they have been split into their
+/// arguments using [`interpolate_commands`](fn.interpolate_commands.html).
+///
+/// Occurrences of undeclared variables are kept as they are, such that e.g. shell commands can
+/// still refer to environment variables.
+pub fn interpolate(config: &mut Value, values: &BTreeMap<String, String>) {
+    if let Value::Table(ref mut table) = *config {
+        for (key, value) in table.iter_mut() {
+            // Defaults are not interpolated themselves
+            if key != "variables" {
+                interpolate_value(value, values);
+            }
+        }
+    }
+}
+
+/// Replace all occurrences of `${name}` in the commands of the given applications with the value
+/// of the variable `name`.
+///
+/// Every argument is interpolated on its own, such that a value containing whitespace is passed
+/// as part of a single argument. Commands run through a shell receive the command as it was
+/// specified, with the values quoted such that the shell takes them literally. This means that
+/// values can't inject shell syntax, but also that e.g. `~` or `$HOME` within a value isn't
+/// expanded by the shell.
+pub fn interpolate_commands<'a, I>(applications: I, values: &BTreeMap<String, String>)
+where
+    I: IntoIterator<Item = &'a mut Application>,
+{
+    for application in applications {
+        interpolate_command(&mut application.command, values);
+    }
+}
+
+/// Replace all occurrences of `${name}` in the commands of the given project configuration,
+/// including its hooks, see [`interpolate_commands`](fn.interpolate_commands.html).
+pub fn interpolate_config_commands(config: &mut Config, values: &BTreeMap<String, String>) {
+    let general = &mut config.general;
+    for hooks in [
+        &mut general.on_start,
+        &mut general.after_start,
+        &mut general.on_stop,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        for command in &mut hooks.commands {
+            interpolate_command(command, values);
+        }
+    }
+
+    interpolate_commands(&mut config.applications, values);
+    for workspace in &mut config.workspaces {
+        interpolate_commands(&mut workspace.applications, values);
+    }
+}
+
+fn interpolate_command(command: &mut ApplicationCommand, values: &BTreeMap<String, String>) {
+    command.program = interpolate_str(&command.program, values);
+    for arg in &mut command.args {
+        *arg = interpolate_str(arg, values);
+    }
+    if let Some(ref mut commandline) = command.commandline {
+        *commandline = interpolate_commandline(commandline, values);
+    }
+}
+
+/// Interpolate a command line that is run through a shell, quoting every value depending on
+/// whether it is used within single quotes, double quotes or no quotes at all.
+fn interpolate_commandline(commandline: &str, values: &BTreeMap<String, String>) -> String {
+    let mut interpolated = String::with_capacity(commandline.len());
+    let mut quote = None;
+    let mut rest = commandline;
+    while let Some(c) = rest.chars().next() {
+        if let Some((value, end)) = lookup(rest, values) {
+            interpolated.push_str(&match quote {
+                None => shlex::quote(value),
+                Some('\'') => value.replace('\'', "'\\''"),
+                Some(_) => value
+                    .chars()
+                    .flat_map(|c| match c {
+                        '\\' | '"' | '$' | '`' => vec!['\\', c],
+                        c => vec![c],
+                    })
+                    .collect(),
+            });
+            rest = &rest[end + 1..];
+            continue;
+        }
+
+        interpolated.push(c);
+        rest = &rest[c.len_utf8()..];
+        match (quote, c) {
+            // A backslash escapes the next character, e.g. `\${name}` is left to the shell
+            (None, '\\') | (Some('"'), '\\') => {
+                if let Some(escaped) = rest.chars().next() {
+                    interpolated.push(escaped);
+                    rest = &rest[escaped.len_utf8()..];
+                }
+            }
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            _ => (),
+        }
+    }
+
+    interpolated
+}
+
+/// Look up the value of the variable referenced as `${name}` at the start of `string`, returning
+/// it along with the index of the closing brace.
+fn lookup<'a>(string: &str, values: &'a BTreeMap<String, String>) -> Option<(&'a String, usize)> {
+    if !string.starts_with("${") {
+        return None;
+    }
+    let end = string.find('}')?;
+    values.get(&string[2..end]).map(|value| (value, end))
+}
+
+fn interpolate_value(value: &mut Value, values: &BTreeMap<String, String>) {
+    match *value {
+        Value::String(ref mut string) => *string = interpolate_str(string, values),
+        Value::Array(ref mut array) => {
+            for value in array {
+                interpolate_value(value, values);
+            }
+        }
+        Value::Table(ref mut table) => {
+            for (key, value) in table.iter_mut() {
+                match key.as_str() {
+                    // Commands are interpolated per argument once they have been split
+                    "command" => (),
+                    "on_start" | "after_start" | "on_stop" => {
+                        if let Value::Table(ref mut hooks) = *value {
+                            for (key, value) in hooks.iter_mut() {
+                                if key != "commands" {
+                                    interpolate_value(value, values);
+                                }
+                            }
+                        }
+                    }
+                    _ => interpolate_value(value, values),
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn interpolate_str(string: &str, values: &BTreeMap<String, String>) -> String {
+    let mut interpolated = String::with_capacity(string.len());
+    let mut rest = string;
+    while let Some(start) = rest.find("${") {
+        interpolated.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match lookup(rest, values) {
+            Some((value, end)) => {
+                interpolated.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                interpolated.push_str("${");
+                rest = &rest[2..];
+            }
+        }
+    }
+    interpolated.push_str(rest);
+
+    interpolated
+}
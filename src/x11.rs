@@ -0,0 +1,974 @@
+// Copyright Pit Kleyersburg <pitkley@googlemail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified or distributed
+// except according to those terms.
+
+//! Module for talking to the X server directly, i.e. looking up the windows of processes,
+//! simulating key-presses through the XTEST extension and owning selections.
+
+use crate::{errors::*, types::PasteSelection};
+use std::{
+    collections::VecDeque,
+    fs::OpenOptions,
+    io,
+    os::unix::io::AsRawFd,
+    ptr, thread,
+    time::{Duration, Instant},
+};
+use x11rb::{
+    connection::{Connection as _, RequestConnection as _},
+    protocol::{
+        xproto::{
+            Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, Keycode, MapState,
+            PropMode, Property, SelectionNotifyEvent, SelectionRequestEvent, WindowClass,
+            KEY_PRESS_EVENT, KEY_RELEASE_EVENT, SELECTION_NOTIFY_EVENT,
+        },
+        xtest::ConnectionExt as _,
+        Event,
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+    COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE,
+};
+
+pub use x11rb::protocol::xproto::{Keysym, Window};
+
+/// Delay between two key-presses, analogous to the default of `xdotool type`.
+const KEY_DELAY: Duration = Duration::from_millis(12);
+
+const KEYSYM_RETURN: Keysym = 0xff0d;
+const KEYSYM_SHIFT_L: Keysym = 0xffe1;
+const KEYSYM_TAB: Keysym = 0xff09;
+
+/// Names of keysyms which don't consist of a single character, as accepted by `xdotool key`.
+const KEYSYMS: &[(&str, Keysym)] = &[
+    // Aliases for modifiers
+    ("alt", 0xffe9),
+    ("ctrl", 0xffe3),
+    ("control", 0xffe3),
+    ("meta", 0xffe7),
+    ("shift", 0xffe1),
+    ("super", 0xffeb),
+    // Modifiers
+    ("Alt_L", 0xffe9),
+    ("Alt_R", 0xffea),
+    ("Control_L", 0xffe3),
+    ("Control_R", 0xffe4),
+    ("Meta_L", 0xffe7),
+    ("Meta_R", 0xffe8),
+    ("Shift_L", 0xffe1),
+    ("Shift_R", 0xffe2),
+    ("Super_L", 0xffeb),
+    ("Super_R", 0xffec),
+    ("ISO_Level3_Shift", 0xfe03),
+    // Function and cursor keys
+    ("BackSpace", 0xff08),
+    ("Tab", 0xff09),
+    ("Return", 0xff0d),
+    ("Pause", 0xff13),
+    ("Escape", 0xff1b),
+    ("Delete", 0xffff),
+    ("Home", 0xff50),
+    ("Left", 0xff51),
+    ("Up", 0xff52),
+    ("Right", 0xff53),
+    ("Down", 0xff54),
+    ("Prior", 0xff55),
+    ("Page_Up", 0xff55),
+    ("Next", 0xff56),
+    ("Page_Down", 0xff56),
+    ("End", 0xff57),
+    ("Insert", 0xff63),
+    ("Menu", 0xff67),
+    ("KP_Enter", 0xff8d),
+    ("F1", 0xffbe),
+    ("F2", 0xffbf),
+    ("F3", 0xffc0),
+    ("F4", 0xffc1),
+    ("F5", 0xffc2),
+    ("F6", 0xffc3),
+    ("F7", 0xffc4),
+    ("F8", 0xffc5),
+    ("F9", 0xffc6),
+    ("F10", 0xffc7),
+    ("F11", 0xffc8),
+    ("F12", 0xffc9),
+    // Latin 1
+    ("space", 0x20),
+    ("exclam", 0x21),
+    ("quotedbl", 0x22),
+    ("numbersign", 0x23),
+    ("dollar", 0x24),
+    ("percent", 0x25),
+    ("ampersand", 0x26),
+    ("apostrophe", 0x27),
+    ("parenleft", 0x28),
+    ("parenright", 0x29),
+    ("asterisk", 0x2a),
+    ("plus", 0x2b),
+    ("comma", 0x2c),
+    ("minus", 0x2d),
+    ("period", 0x2e),
+    ("slash", 0x2f),
+    ("colon", 0x3a),
+    ("semicolon", 0x3b),
+    ("less", 0x3c),
+    ("equal", 0x3d),
+    ("greater", 0x3e),
+    ("question", 0x3f),
+    ("at", 0x40),
+    ("bracketleft", 0x5b),
+    ("backslash", 0x5c),
+    ("bracketright", 0x5d),
+    ("asciicircum", 0x5e),
+    ("underscore", 0x5f),
+    ("grave", 0x60),
+    ("braceleft", 0x7b),
+    ("bar", 0x7c),
+    ("braceright", 0x7d),
+    ("asciitilde", 0x7e),
+];
+
+/// A connection to the X server.
+pub struct Connection {
+    connection: RustConnection,
+    root: Window,
+    net_wm_pid: Atom,
+    mapping: KeyboardMapping,
+}
+
+impl Connection {
+    /// Connect to the X server given in the `DISPLAY` environment variable.
+    pub fn connect() -> Result<Self> {
+        let (connection, screen) = x11rb::connect(None)?;
+        let root = connection.setup().roots[screen].root;
+        let min_keycode = connection.setup().min_keycode;
+        let max_keycode = connection.setup().max_keycode;
+
+        let mapping = connection
+            .get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)?
+            .reply()?;
+        let net_wm_pid = connection.intern_atom(false, b"_NET_WM_PID")?.reply()?.atom;
+
+        Ok(Connection {
+            connection,
+            root,
+            net_wm_pid,
+            mapping: KeyboardMapping {
+                min_keycode,
+                keysyms_per_keycode: mapping.keysyms_per_keycode as usize,
+                keysyms: mapping.keysyms,
+            },
+        })
+    }
+
+    /// Get the process id of the application owning `window`, according to the window's
+    /// `_NET_WM_PID` property.
+    ///
+    /// Windows can vanish while they are being looked at, which is why any error results in
+    /// `None`.
+    pub fn window_pid(&self, window: Window) -> Option<u32> {
+        self.connection
+            .get_property(false, window, self.net_wm_pid, AtomEnum::CARDINAL, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?
+            .value32()?
+            .next()
+    }
+
+    /// Find a viewable window that belongs to the process `pid`, according to its `_NET_WM_PID`
+    /// property.
+    pub fn find_window(&self, pid: u32) -> Result<Option<Window>> {
+        let mut windows = VecDeque::new();
+        windows.push_back(self.root);
+
+        while let Some(window) = windows.pop_front() {
+            if self.window_pid(window) == Some(pid) {
+                let map_state = self
+                    .connection
+                    .get_window_attributes(window)?
+                    .reply()
+                    .ok()
+                    .map(|attributes| attributes.map_state);
+                if map_state == Some(MapState::VIEWABLE) {
+                    return Ok(Some(window));
+                }
+            }
+
+            if let Ok(tree) = self.connection.query_tree(window)?.reply() {
+                windows.extend(tree.children);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Get the window that currently has the input focus.
+    pub fn focused_window(&self) -> Result<Window> {
+        Ok(self.connection.get_input_focus()?.reply()?.focus)
+    }
+
+    /// Press the keys producing `keysyms` in order and release them in reverse. The key-presses
+    /// are received by the focused window.
+    ///
+    /// Keysyms that the keyboard layout doesn't contain are temporarily mapped to an unused
+    /// keycode. The mapping is restored even if pressing the keys fails.
+    pub fn chord(&mut self, keysyms: &[Keysym]) -> Result<()> {
+        let mut remapped = vec![];
+        let result = self.press(keysyms, &mut remapped);
+        if remapped.is_empty() {
+            return result;
+        }
+
+        // The application has to process the key-presses before the keycodes are unmapped again
+        thread::sleep(Duration::from_millis(50));
+        let restored = remapped
+            .into_iter()
+            .map(|keycode| self.unmap(keycode))
+            .fold(Ok(()), Result::and)
+            .and_then(|()| Ok(self.connection.sync()?));
+
+        result.and(restored)
+    }
+
+    /// Resolve `keysyms` to keycodes and press and release them, adding every keycode that had to
+    /// be remapped to `remapped`.
+    fn press(&mut self, keysyms: &[Keysym], remapped: &mut Vec<Keycode>) -> Result<()> {
+        let mut keycodes = vec![];
+        for &keysym in keysyms {
+            match self.mapping.keycode(keysym) {
+                Some((keycode, shift)) => {
+                    if shift {
+                        if let Some((shift, _)) = self.mapping.keycode(KEYSYM_SHIFT_L) {
+                            keycodes.push(shift);
+                        }
+                    }
+                    keycodes.push(keycode);
+                }
+                None => {
+                    let keycode = self.remap(keysym)?;
+                    remapped.push(keycode);
+                    keycodes.push(keycode);
+                }
+            }
+        }
+
+        for &keycode in &keycodes {
+            self.connection.xtest_fake_input(
+                KEY_PRESS_EVENT,
+                keycode,
+                CURRENT_TIME,
+                self.root,
+                0,
+                0,
+                0,
+            )?;
+        }
+        for &keycode in keycodes.iter().rev() {
+            self.connection.xtest_fake_input(
+                KEY_RELEASE_EVENT,
+                keycode,
+                CURRENT_TIME,
+                self.root,
+                0,
+                0,
+                0,
+            )?;
+        }
+        self.connection.sync()?;
+        thread::sleep(KEY_DELAY);
+
+        Ok(())
+    }
+
+    /// Temporarily map `keysym` to a keycode that isn't mapped to anything, to be able to input
+    /// keysyms that the keyboard layout doesn't contain.
+    fn remap(&mut self, keysym: Keysym) -> Result<Keycode> {
+        let keycode = self
+            .mapping
+            .unused_keycode()
+            .ok_or(ErrorKind::TextOrKeyInputFailed)?;
+        self.set_keysyms(keycode, keysym)?;
+
+        Ok(keycode)
+    }
+
+    /// Undo [`remap`](#method.remap) for `keycode`.
+    fn unmap(&mut self, keycode: Keycode) -> Result<()> {
+        self.set_keysyms(keycode, 0)
+    }
+
+    fn set_keysyms(&mut self, keycode: Keycode, keysym: Keysym) -> Result<()> {
+        let keysyms_per_keycode = self.mapping.keysyms_per_keycode;
+        let keysyms = vec![keysym; keysyms_per_keycode];
+        self.connection
+            .change_keyboard_mapping(1, keycode, keysyms_per_keycode as u8, &keysyms)?;
+
+        let index = (keycode - self.mapping.min_keycode) as usize;
+        self.mapping.keysyms[index * keysyms_per_keycode..(index + 1) * keysyms_per_keycode]
+            .copy_from_slice(&keysyms);
+
+        Ok(())
+    }
+}
+
+/// An X selection (e.g. the clipboard), accessed through a window of its own.
+///
+/// Text is read by converting the selection to `UTF8_STRING`, and served to other applications
+/// as `UTF8_STRING`, `STRING` or `TEXT` while the selection is owned.
+pub struct Selection {
+    connection: RustConnection,
+    window: Window,
+    selection: Atom,
+    atoms: SelectionAtoms,
+    contents: Vec<u8>,
+}
+
+struct SelectionAtoms {
+    targets: Atom,
+    utf8_string: Atom,
+    text: Atom,
+    incr: Atom,
+    property: Atom,
+}
+
+impl Selection {
+    /// Connect to the X server given in the `DISPLAY` environment variable to access `selection`.
+    pub fn connect(selection: PasteSelection) -> Result<Self> {
+        let (connection, screen) = x11rb::connect(None)?;
+        let root = connection.setup().roots[screen].root;
+        let intern_atom = |name: &[u8]| -> Result<Atom> {
+            Ok(connection.intern_atom(false, name)?.reply()?.atom)
+        };
+
+        let selection = intern_atom(match selection {
+            PasteSelection::Clipboard => b"CLIPBOARD",
+            PasteSelection::Primary => b"PRIMARY",
+        })?;
+        let atoms = SelectionAtoms {
+            targets: intern_atom(b"TARGETS")?,
+            utf8_string: intern_atom(b"UTF8_STRING")?,
+            text: intern_atom(b"TEXT")?,
+            incr: intern_atom(b"INCR")?,
+            property: intern_atom(b"I3NATOR_SELECTION")?,
+        };
+
+        // The window is never mapped, it only receives the selection events
+        let window = connection.generate_id()?;
+        connection.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+        connection.flush()?;
+
+        Ok(Selection {
+            connection,
+            window,
+            selection,
+            atoms,
+            contents: vec![],
+        })
+    }
+
+    /// Get the text contents of the selection.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is:
+    ///
+    /// - `Ok`: the text contents, or `None` if the selection isn't owned by anyone, doesn't
+    ///   contain text or its owner didn't provide it within `timeout`.
+    /// - `Err`: an error, if the X server couldn't be talked to.
+    pub fn read(&self, timeout: Duration) -> Result<Option<Vec<u8>>> {
+        let owner = self
+            .connection
+            .get_selection_owner(self.selection)?
+            .reply()?
+            .owner;
+        if owner == NONE {
+            return Ok(None);
+        } else if owner == self.window {
+            return Ok(Some(self.contents.to_owned()));
+        }
+
+        let deadline = Instant::now().checked_add(timeout);
+        self.connection.convert_selection(
+            self.window,
+            self.selection,
+            self.atoms.utf8_string,
+            self.atoms.property,
+            CURRENT_TIME,
+        )?;
+        self.connection.flush()?;
+        loop {
+            match self.next_event(deadline)? {
+                Some(Event::SelectionNotify(event)) if event.requestor == self.window => {
+                    // The owner refuses the conversion if the contents aren't text
+                    if event.property == NONE {
+                        return Ok(None);
+                    }
+                    break;
+                }
+                Some(_) => (),
+                None => return Ok(None),
+            }
+        }
+
+        let reply = self
+            .connection
+            .get_property(
+                true,
+                self.window,
+                self.atoms.property,
+                AtomEnum::ANY,
+                0,
+                u32::MAX,
+            )?
+            .reply()?;
+        if reply.type_ == self.atoms.utf8_string {
+            return Ok(Some(reply.value));
+        } else if reply.type_ != self.atoms.incr {
+            return Ok(None);
+        }
+
+        // Large contents are transferred in chunks, every one of them is announced by a new value
+        // of the property. The transfer ends with an empty chunk.
+        let mut contents = vec![];
+        loop {
+            match self.next_event(deadline)? {
+                Some(Event::PropertyNotify(event))
+                    if event.atom == self.atoms.property && event.state == Property::NEW_VALUE =>
+                {
+                    let chunk = self
+                        .connection
+                        .get_property(
+                            true,
+                            self.window,
+                            self.atoms.property,
+                            AtomEnum::ANY,
+                            0,
+                            u32::MAX,
+                        )?
+                        .reply()?;
+                    if chunk.value.is_empty() {
+                        return Ok(Some(contents));
+                    }
+                    contents.extend(chunk.value);
+                }
+                Some(_) => (),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Own the selection, providing the text `contents` to applications requesting them.
+    ///
+    /// The requests are only answered while [`serve`](#method.serve) is running.
+    pub fn own(&mut self, contents: &[u8]) -> Result<()> {
+        self.contents = contents.to_owned();
+        self.connection
+            .set_selection_owner(self.window, self.selection, CURRENT_TIME)?;
+        let owner = self
+            .connection
+            .get_selection_owner(self.selection)?
+            .reply()?
+            .owner;
+        if owner != self.window {
+            return Err(ErrorKind::TextOrKeyInputFailed.into());
+        }
+
+        Ok(())
+    }
+
+    /// Answer the requests for the contents of the owned selection until one of them requested
+    /// the contents as text, or until `deadline` if there is one.
+    ///
+    /// Returns whether the contents were requested. They aren't if the deadline passed or another
+    /// application took over the selection.
+    pub fn serve(&mut self, deadline: Option<Instant>) -> Result<bool> {
+        loop {
+            match self.next_event(deadline)? {
+                Some(Event::SelectionRequest(request)) => {
+                    if self.answer(&request)? {
+                        return Ok(true);
+                    }
+                }
+                Some(Event::SelectionClear(event)) if event.selection == self.selection => {
+                    return Ok(false)
+                }
+                Some(_) => (),
+                None => return Ok(false),
+            }
+        }
+    }
+
+    /// Answer the requests for the contents of the owned selection in a detached process, until
+    /// another application takes over the selection.
+    ///
+    /// Like `xclip`, the process outlives i3nator, as the X server drops the selection once the
+    /// connection owning it is closed.
+    pub fn serve_detached(mut self) -> Result<()> {
+        // Forking twice reparents the serving process to init, such that it doesn't have to be
+        // waited for
+        match unsafe { libc::fork() } {
+            -1 => Err(io::Error::last_os_error().into()),
+            0 => {
+                if unsafe { libc::setsid() != -1 && libc::fork() == 0 } {
+                    // Don't keep e.g. a pipe the output of i3nator is read from open
+                    if let Ok(null) = OpenOptions::new().read(true).write(true).open("/dev/null") {
+                        for fd in 0..3 {
+                            unsafe { libc::dup2(null.as_raw_fd(), fd) };
+                        }
+                    }
+                    while let Ok(true) = self.serve(None) {}
+                }
+                unsafe { libc::_exit(0) }
+            }
+            child => {
+                unsafe { libc::waitpid(child, ptr::null_mut(), 0) };
+                Ok(())
+            }
+        }
+    }
+
+    /// Clear the selection if it is still owned, such that it isn't owned by anyone.
+    pub fn clear(&self) -> Result<()> {
+        let owner = self
+            .connection
+            .get_selection_owner(self.selection)?
+            .reply()?
+            .owner;
+        if owner == self.window {
+            self.connection
+                .set_selection_owner(NONE, self.selection, CURRENT_TIME)?;
+            self.connection.sync()?;
+        }
+
+        Ok(())
+    }
+
+    /// Answer `request`, returning whether the contents were provided as text.
+    fn answer(&self, request: &SelectionRequestEvent) -> Result<bool> {
+        let text_targets = [
+            self.atoms.utf8_string,
+            AtomEnum::STRING.into(),
+            self.atoms.text,
+        ];
+
+        // Obsolete clients don't specify a property, the target is used instead
+        let property = if request.property == NONE {
+            request.target
+        } else {
+            request.property
+        };
+        // Contents too large for a single request would have to be transferred in chunks, which
+        // isn't supported
+        let fits = self.contents.len() + 24 <= self.connection.maximum_request_bytes();
+
+        let (property, provided) = if request.selection != self.selection {
+            (NONE, false)
+        } else if request.target == self.atoms.targets {
+            let mut targets = vec![self.atoms.targets];
+            targets.extend(text_targets.iter());
+            self.connection.change_property32(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                AtomEnum::ATOM,
+                &targets,
+            )?;
+            (property, false)
+        } else if text_targets.contains(&request.target) && fits {
+            let type_ = if request.target == self.atoms.text {
+                self.atoms.utf8_string
+            } else {
+                request.target
+            };
+            self.connection.change_property8(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                type_,
+                &self.contents,
+            )?;
+            (property, true)
+        } else {
+            (NONE, false)
+        };
+
+        self.connection.send_event(
+            false,
+            request.requestor,
+            EventMask::NO_EVENT,
+            SelectionNotifyEvent {
+                response_type: SELECTION_NOTIFY_EVENT,
+                sequence: 0,
+                time: request.time,
+                requestor: request.requestor,
+                selection: request.selection,
+                target: request.target,
+                property,
+            },
+        )?;
+        self.connection.flush()?;
+
+        Ok(provided)
+    }
+
+    /// Get the next event, waiting for it until `deadline` if there is one.
+    fn next_event(&self, deadline: Option<Instant>) -> Result<Option<Event>> {
+        loop {
+            if let Some(event) = self.connection.poll_for_event()? {
+                return Ok(Some(event));
+            }
+            if matches!(deadline, Some(deadline) if Instant::now() >= deadline) {
+                return Ok(None);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+/// The keysyms every keycode of the keyboard produces, as reported by the X server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyboardMapping {
+    /// The first keycode the mapping starts at.
+    pub min_keycode: Keycode,
+    /// The number of keysyms every keycode has, one per shift level and group.
+    pub keysyms_per_keycode: usize,
+    /// The keysyms of all keycodes, `keysyms_per_keycode` at a time. Unused entries are `0`.
+    pub keysyms: Vec<Keysym>,
+}
+
+impl KeyboardMapping {
+    /// Find the keycode producing `keysym`, and whether shift has to be held for it.
+    ///
+    /// Only the first two levels are considered, i.e. keysyms which require e.g. `AltGr` are not
+    /// found.
+    pub fn keycode(&self, keysym: Keysym) -> Option<(Keycode, bool)> {
+        self.keysyms
+            .chunks(self.keysyms_per_keycode)
+            .enumerate()
+            .find_map(|(index, keysyms)| {
+                keysyms
+                    .iter()
+                    .take(2)
+                    .position(|&k| k == keysym)
+                    .map(|level| (self.min_keycode + index as Keycode, level == 1))
+            })
+    }
+
+    /// Find a keycode that isn't mapped to any keysym.
+    pub fn unused_keycode(&self) -> Option<Keycode> {
+        self.keysyms
+            .chunks(self.keysyms_per_keycode)
+            .position(|keysyms| keysyms.iter().all(|&k| k == 0))
+            .map(|index| self.min_keycode + index as Keycode)
+    }
+}
+
+/// Get the keysym for the character `c`.
+///
+/// Latin 1 characters correspond to the keysym with the same value, all other characters are
+/// mapped to the Unicode keysyms (offset by `0x1000000`).
+pub fn char_keysym(c: char) -> Keysym {
+    match c {
+        '\n' => KEYSYM_RETURN,
+        '\t' => KEYSYM_TAB,
+        ' '..='~' | '\u{a0}'..='\u{ff}' => c as Keysym,
+        _ => 0x0100_0000 + c as Keysym,
+    }
+}
+
+/// Get the keysym for a key name as accepted by `xdotool key`, i.e. a single character, the name
+/// of a keysym or its value in hexadecimal (`0xff0d`).
+///
+/// # Returns
+///
+/// A `Result` which is:
+///
+/// - `Ok`: the keysym.
+/// - `Err`: [`ErrorKind::UnknownKey`][ErrorKind-UnknownKey] if the name isn't known.
+///
+///
+/// [ErrorKind-UnknownKey]: ../errors/enum.ErrorKind.html#variant.UnknownKey
+pub fn keysym(name: &str) -> Result<Keysym> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(char_keysym(c));
+    }
+
+    KEYSYMS
+        .iter()
+        .find(|(keysym, _)| *keysym == name)
+        .map(|&(_, keysym)| keysym)
+        .or_else(|| {
+            name.strip_prefix("0x")
+                .and_then(|hex| Keysym::from_str_radix(hex, 16).ok())
+        })
+        .ok_or_else(|| ErrorKind::UnknownKey(name.to_owned()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyboardMapping;
+    use crate::{errors::*, x11};
+
+    #[test]
+    fn keysym_names() {
+        assert_eq!(x11::keysym("Return").unwrap(), 0xff0d);
+        assert_eq!(x11::keysym("ctrl").unwrap(), 0xffe3);
+        assert_eq!(x11::keysym("Control_L").unwrap(), 0xffe3);
+        assert_eq!(x11::keysym("F12").unwrap(), 0xffc9);
+        assert_eq!(x11::keysym("space").unwrap(), 0x20);
+        assert_eq!(x11::keysym("braceleft").unwrap(), 0x7b);
+    }
+
+    #[test]
+    fn keysym_characters() {
+        assert_eq!(x11::keysym("a").unwrap(), 0x61);
+        assert_eq!(x11::keysym("A").unwrap(), 0x41);
+        assert_eq!(x11::keysym("+").unwrap(), 0x2b);
+        assert_eq!(x11::keysym("ä").unwrap(), 0xe4);
+        assert_eq!(x11::keysym("€").unwrap(), 0x0100_20ac);
+    }
+
+    #[test]
+    fn keysym_hex() {
+        assert_eq!(x11::keysym("0xff0d").unwrap(), 0xff0d);
+        assert_eq!(x11::keysym("0x1000041").unwrap(), 0x0100_0041);
+    }
+
+    #[test]
+    fn keysym_unknown() {
+        for name in &["Retrun", "0x", "0xzz", "ctrl+a", ""] {
+            match x11::keysym(name) {
+                Err(Error(ErrorKind::UnknownKey(key), _)) => assert_eq!(key, *name),
+                other => panic!("expected UnknownKey for '{}', got {:?}", name, other),
+            }
+        }
+    }
+
+    #[test]
+    fn char_keysym() {
+        assert_eq!(x11::char_keysym('\n'), 0xff0d);
+        assert_eq!(x11::char_keysym('\t'), 0xff09);
+        assert_eq!(x11::char_keysym(' '), 0x20);
+        assert_eq!(x11::char_keysym('~'), 0x7e);
+        assert_eq!(x11::char_keysym('\u{a0}'), 0xa0);
+        assert_eq!(x11::char_keysym('ÿ'), 0xff);
+        // Characters outside of Latin 1 are offset into the Unicode keysyms
+        assert_eq!(x11::char_keysym('Ā'), 0x0100_0100);
+        assert_eq!(x11::char_keysym('\u{1f600}'), 0x0101_f600);
+    }
+
+    fn mapping() -> KeyboardMapping {
+        KeyboardMapping {
+            min_keycode: 8,
+            keysyms_per_keycode: 4,
+            keysyms: vec![
+                // 8: a A
+                0x61, 0x41, 0, 0, //
+                // 9: unused
+                0, 0, 0, 0, //
+                // 10: 1 !, with a third level
+                0x31, 0x21, 0xb9, 0, //
+                // 11: Shift_L
+                0xffe1, 0, 0, 0,
+            ],
+        }
+    }
+
+    #[test]
+    fn keyboard_mapping_keycode() {
+        let mapping = mapping();
+        assert_eq!(mapping.keycode(0x61), Some((8, false)));
+        assert_eq!(mapping.keycode(0x41), Some((8, true)));
+        assert_eq!(mapping.keycode(0x31), Some((10, false)));
+        assert_eq!(mapping.keycode(0x21), Some((10, true)));
+        assert_eq!(mapping.keycode(0xffe1), Some((11, false)));
+        // Only the first two levels are considered
+        assert_eq!(mapping.keycode(0xb9), None);
+        assert_eq!(mapping.keycode(0x62), None);
+    }
+
+    #[test]
+    fn keyboard_mapping_unused_keycode() {
+        assert_eq!(mapping().unused_keycode(), Some(9));
+
+        let mut mapping = mapping();
+        mapping.keysyms[4] = 0x62;
+        assert_eq!(mapping.unused_keycode(), None);
+    }
+
+    #[cfg(feature = "xvfb-tests")]
+    #[test]
+    fn xvfb_find_window_and_chord() {
+        use crate::types::PasteSelection;
+        use std::{
+            env, process, thread,
+            time::{Duration, Instant},
+        };
+        use x11rb::{
+            connection::Connection as _,
+            protocol::{xproto::*, Event},
+            wrapper::ConnectionExt as _,
+            COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME,
+        };
+
+        let display = ":57";
+        let mut xvfb = process::Command::new("Xvfb")
+            .arg(display)
+            .spawn()
+            .expect("couldn't start Xvfb");
+        env::set_var("DISPLAY", display);
+
+        let start = Instant::now();
+        let (connection, screen) = loop {
+            match x11rb::connect(Some(display)) {
+                Ok(connection) => break connection,
+                Err(_) if start.elapsed() < Duration::from_secs(10) => {
+                    thread::sleep(Duration::from_millis(100))
+                }
+                Err(e) => panic!("couldn't connect to Xvfb: {}", e),
+            }
+        };
+        let root = connection.setup().roots[screen].root;
+        let min_keycode = connection.setup().min_keycode;
+        let max_keycode = connection.setup().max_keycode;
+        let get_mapping = || {
+            let mapping = connection
+                .get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)
+                .unwrap()
+                .reply()
+                .unwrap();
+            KeyboardMapping {
+                min_keycode,
+                keysyms_per_keycode: mapping.keysyms_per_keycode as usize,
+                keysyms: mapping.keysyms,
+            }
+        };
+        let mapping = get_mapping();
+
+        // Open a window pretending to belong to this process and focus it
+        let window = connection.generate_id().unwrap();
+        connection
+            .create_window(
+                COPY_DEPTH_FROM_PARENT,
+                window,
+                root,
+                0,
+                0,
+                100,
+                100,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                COPY_FROM_PARENT,
+                &CreateWindowAux::new().event_mask(EventMask::KEY_PRESS),
+            )
+            .unwrap();
+        let net_wm_pid = connection
+            .intern_atom(false, b"_NET_WM_PID")
+            .unwrap()
+            .reply()
+            .unwrap()
+            .atom;
+        connection
+            .change_property32(
+                PropMode::REPLACE,
+                window,
+                net_wm_pid,
+                AtomEnum::CARDINAL,
+                &[process::id()],
+            )
+            .unwrap();
+        connection.map_window(window).unwrap();
+        connection
+            .set_input_focus(InputFocus::PARENT, window, CURRENT_TIME)
+            .unwrap();
+        connection.sync().unwrap();
+
+        let mut x11 = x11::Connection::connect().unwrap();
+        assert_eq!(x11.window_pid(window), Some(process::id()));
+        assert_eq!(x11.window_pid(root), None);
+        assert_eq!(x11.find_window(process::id()).unwrap(), Some(window));
+        assert_eq!(x11.find_window(process::id() + 1).unwrap(), None);
+        assert_eq!(x11.focused_window().unwrap(), window);
+
+        // `€` isn't part of the keyboard layout and has to be remapped to an unused keycode
+        for c in "aB€".chars() {
+            x11.chord(&[x11::char_keysym(c)]).unwrap();
+        }
+        assert_eq!(get_mapping(), mapping);
+
+        let shift = mapping.keycode(0xffe1).unwrap().0;
+        let mut presses = vec![];
+        let start = Instant::now();
+        while presses.len() < 3 && start.elapsed() < Duration::from_secs(5) {
+            match connection.poll_for_event().unwrap() {
+                Some(Event::KeyPress(event)) if event.detail != shift => {
+                    assert_eq!(event.event, window);
+                    presses.push((
+                        event.detail,
+                        event.state & u16::from(KeyButMask::SHIFT) != 0,
+                    ));
+                }
+                Some(_) => (),
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+
+        // A selection owned through one connection can be read as text through another
+        let mut owner = x11::Selection::connect(PasteSelection::Clipboard).unwrap();
+        owner.own("echo ✓".as_bytes()).unwrap();
+        let serving = thread::spawn(move || {
+            let requested = owner
+                .serve(Instant::now().checked_add(Duration::from_secs(5)))
+                .unwrap();
+            (owner, requested)
+        });
+        let mut reader = x11::Selection::connect(PasteSelection::Clipboard).unwrap();
+        let contents = reader.read(Duration::from_secs(5)).unwrap();
+        let (owner, requested) = serving.join().unwrap();
+        owner.clear().unwrap();
+        let cleared = reader.read(Duration::from_secs(1)).unwrap();
+
+        // Contents served by a detached process remain available once the owning connection is
+        // gone, until another application takes over the selection
+        let mut owner = x11::Selection::connect(PasteSelection::Clipboard).unwrap();
+        owner.own(b"previous").unwrap();
+        owner.serve_detached().unwrap();
+        let detached = reader.read(Duration::from_secs(5)).unwrap();
+        reader.own(b"next").unwrap();
+
+        xvfb.kill().unwrap();
+        xvfb.wait().unwrap();
+
+        assert_eq!(contents, Some("echo ✓".as_bytes().to_vec()));
+        assert!(requested);
+        assert_eq!(cleared, None);
+        assert_eq!(detached, Some(b"previous".to_vec()));
+
+        assert_eq!(
+            presses,
+            vec![
+                mapping.keycode(0x61).unwrap(),
+                mapping.keycode(0x42).unwrap(),
+                (mapping.unused_keycode().unwrap(), false),
+            ]
+        );
+    }
+}
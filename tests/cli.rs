@@ -0,0 +1,51 @@
+// Copyright Pit Kleyersburg <pitkley@googlemail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified or distributed
+// except according to those terms.
+
+use std::process::{Command, Output};
+use tempdir::TempDir;
+
+fn i3nator(args: &[&str]) -> Output {
+    let config_dir = TempDir::new("i3nator-tests-cli").unwrap();
+    Command::new(env!("CARGO_BIN_EXE_i3nator"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", config_dir.path())
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .unwrap()
+}
+
+fn timeout_help(subcommand: &str) -> String {
+    let output = i3nator(&[subcommand, "--help"]);
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .split("--timeout")
+        .nth(1)
+        .and_then(|help| help.split("[default: ").nth(1))
+        .and_then(|default| default.split(']').next())
+        .unwrap()
+        .to_owned()
+}
+
+#[test]
+fn restart_timeout_defaults_to_stop_timeout() {
+    assert_eq!(timeout_help("start"), timeout_help("stop"));
+}
+
+#[test]
+fn restart_timeout() {
+    // Parsing succeeds, starting fails because the project doesn't exist
+    let output = i3nator(&["start", "--restart", "--timeout", "3", "unknown"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("'unknown'"));
+
+    // The timeout only applies when restarting
+    let output = i3nator(&["start", "--timeout", "3", "unknown"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--restart"));
+}
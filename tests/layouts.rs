@@ -0,0 +1,204 @@
+// Copyright Pit Kleyersburg <pitkley@googlemail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified or distributed
+// except according to those terms.
+
+use i3ipc::reply::{Node, NodeBorder, NodeLayout, NodeType, WindowProperty};
+use i3nator::layouts;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+fn container(id: i64, nodetype: NodeType, nodes: Vec<Node>) -> Node {
+    Node {
+        focus: vec![],
+        nodes,
+        floating_nodes: vec![],
+        id,
+        name: None,
+        nodetype,
+        border: NodeBorder::Normal,
+        current_border_width: 2,
+        layout: NodeLayout::SplitH,
+        percent: None,
+        rect: (0, 0, 800, 600),
+        window_rect: (0, 0, 0, 0),
+        deco_rect: (0, 0, 0, 0),
+        geometry: (0, 0, 0, 0),
+        window: None,
+        window_properties: None,
+        urgent: false,
+        focused: false,
+    }
+}
+
+fn window(id: i64, name: &str, properties: &[(WindowProperty, &str)]) -> Node {
+    Node {
+        name: Some(name.to_owned()),
+        window: Some(id as i32),
+        window_properties: Some(
+            properties
+                .iter()
+                .map(|(property, value)| (property.to_owned(), (*value).to_owned()))
+                .collect::<HashMap<_, _>>(),
+        ),
+        percent: Some(0.5),
+        ..container(id, NodeType::Con, vec![])
+    }
+}
+
+fn parse(layout: &str) -> Vec<Value> {
+    serde_json::Deserializer::from_str(layout)
+        .into_iter::<Value>()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn workspace_layout() {
+    let mut split = container(
+        2,
+        NodeType::Con,
+        vec![
+            window(
+                3,
+                "a.b [1]",
+                &[
+                    (WindowProperty::Class, "Firefox (dev)"),
+                    (WindowProperty::Title, "a.b [1]"),
+                    (WindowProperty::TransientFor, "0"),
+                ],
+            ),
+            window(
+                4,
+                "term",
+                &[
+                    (WindowProperty::Instance, "x*y"),
+                    (WindowProperty::WindowRole, "^role$"),
+                ],
+            ),
+        ],
+    );
+    split.layout = NodeLayout::SplitV;
+    split.percent = Some(0.6);
+
+    let mut floating = container(
+        5,
+        NodeType::FloatingCon,
+        vec![window(6, "calc", &[(WindowProperty::Class, "Calc+")])],
+    );
+    floating.rect = (10, 20, 300, 200);
+
+    let mut workspace = container(1, NodeType::Workspace, vec![split]);
+    workspace.floating_nodes = vec![floating];
+
+    let expected = vec![
+        json!({
+            "type": "con",
+            "border": "normal",
+            "current_border_width": 2,
+            "percent": 0.6,
+            "layout": "splitv",
+            "nodes": [
+                {
+                    "type": "con",
+                    "border": "normal",
+                    "current_border_width": 2,
+                    "percent": 0.5,
+                    "name": "a.b [1]",
+                    "swallows": [{
+                        "class": "^Firefox \\(dev\\)$",
+                        "title": "^a\\.b \\[1\\]$",
+                    }],
+                },
+                {
+                    "type": "con",
+                    "border": "normal",
+                    "current_border_width": 2,
+                    "percent": 0.5,
+                    "name": "term",
+                    "swallows": [{
+                        "instance": "^x\\*y$",
+                        "window_role": "^\\^role\\$$",
+                    }],
+                },
+            ],
+        }),
+        json!({
+            "type": "floating_con",
+            "border": "normal",
+            "current_border_width": 2,
+            "rect": { "x": 10, "y": 20, "width": 300, "height": 200 },
+            "layout": "splith",
+            "nodes": [{
+                "type": "con",
+                "border": "normal",
+                "current_border_width": 2,
+                "percent": 0.5,
+                "name": "calc",
+                "swallows": [{ "class": "^Calc\\+$" }],
+            }],
+        }),
+    ];
+
+    let actual = layouts::workspace_layout(&workspace).unwrap();
+    assert_eq!(parse(&actual), expected);
+}
+
+#[test]
+fn workspace_layout_drops_containers_without_windows() {
+    let mut floating = container(
+        8,
+        NodeType::FloatingCon,
+        vec![container(9, NodeType::Con, vec![])],
+    );
+    floating.rect = (10, 20, 300, 200);
+
+    let mut workspace = container(
+        1,
+        NodeType::Workspace,
+        vec![
+            // An empty leaf container
+            container(2, NodeType::Con, vec![]),
+            // A split container holding a window and an empty container
+            container(
+                3,
+                NodeType::Con,
+                vec![
+                    window(4, "editor", &[(WindowProperty::Class, "Editor")]),
+                    container(5, NodeType::Con, vec![]),
+                ],
+            ),
+            // A split container holding only empty containers
+            container(6, NodeType::Con, vec![container(7, NodeType::Con, vec![])]),
+        ],
+    );
+    workspace.floating_nodes = vec![floating];
+
+    let expected = vec![json!({
+        "type": "con",
+        "border": "normal",
+        "current_border_width": 2,
+        "layout": "splith",
+        "nodes": [{
+            "type": "con",
+            "border": "normal",
+            "current_border_width": 2,
+            "percent": 0.5,
+            "name": "editor",
+            "swallows": [{ "class": "^Editor$" }],
+        }],
+    })];
+
+    let actual = layouts::workspace_layout(&workspace).unwrap();
+    assert_eq!(parse(&actual), expected);
+
+    let empty = container(
+        1,
+        NodeType::Workspace,
+        vec![container(2, NodeType::Con, vec![])],
+    );
+    assert_eq!(layouts::workspace_layout(&empty).unwrap(), "");
+}
@@ -8,19 +8,30 @@
 
 #![cfg(feature = "sequential-tests")]
 
+use i3ipc::{
+    reply::{Node, NodeBorder, NodeLayout, NodeType, WindowProperty},
+    I3Connection,
+};
 use i3nator::{
     configfiles::ConfigFile,
-    projects::{self, Project},
-    types::*,
+    errors::*,
+    fragments::{self, Fragment},
+    layouts::Layout as ManagedLayout,
+    projects::{self, Project, Selection},
+    types::{self, *},
 };
 use lazy_static::lazy_static;
 use std::{
+    collections::BTreeMap,
     env,
     ffi::OsString,
     fs::{self, File},
     io::prelude::*,
+    os::unix::net::UnixListener,
     panic::{self, UnwindSafe},
     path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
 };
 use tempdir::TempDir;
 use tempfile::NamedTempFile;
@@ -157,7 +168,7 @@ fn open_unknown_project() {
 fn config() {
     with_projects_dir(|projects_dir| {
         let template = r#"[general]
-                          layout = "{ ... }"
+                          layout = '{ "swallows": [ { "class": "^mycommand$" } ] }'
 
                           [[applications]]
                           command = "mycommand""#;
@@ -168,21 +179,48 @@ fn config() {
         assert_eq!(project.path, projects_dir.join("project-template.toml"));
         assert!(project.path.exists());
         assert!(project.verify().is_ok());
+        assert!(project.warnings().is_empty());
 
         let expected = Config {
             general: General {
                 working_directory: None,
                 workspace: None,
-                layout: Layout::Contents("{ ... }".to_owned()),
+                layout: Some(Layout::Contents(
+                    r#"{ "swallows": [ { "class": "^mycommand$" } ] }"#.to_owned(),
+                )),
+                output: vec![],
+                env: BTreeMap::new(),
+                unset_env: vec![],
+                env_file: vec![],
+                extends: None,
+                on_start: None,
+                after_start: None,
+                on_stop: None,
             },
             applications: vec![Application {
                 command: ApplicationCommand {
                     program: "mycommand".to_owned(),
                     args: vec![],
+                    commandline: None,
                 },
                 working_directory: None,
                 exec: None,
+                window_timeout: Duration::from_secs(5),
+                env: BTreeMap::new(),
+                unset_env: vec![],
+                env_file: vec![],
+                name: None,
+                depends_on: vec![],
+                wait_for_window: true,
+                wait_for_port: None,
+                wait_for_file: None,
+                ready_timeout: Duration::from_secs(30),
+                delay: Duration::from_secs(0),
+                shell: None,
             }],
+            workspaces: vec![],
+            variables: BTreeMap::new(),
+            include: vec![],
         };
 
         assert_eq!(project.config().unwrap(), &expected);
@@ -204,6 +242,936 @@ fn config_invalid() {
     })
 }
 
+#[test]
+fn verify_invalid_layout() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          layout = '{ "type": "con", }'
+
+                          [[applications]]
+                          command = "mycommand""#;
+        let project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+
+        assert!(project.verify().is_err());
+        assert!(project.warnings().is_empty());
+    })
+}
+
+#[test]
+fn verify_managed_layout() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          layout = "unknown-layout"
+
+                          [[applications]]
+                          command = "mycommand""#;
+        let project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+
+        assert!(project.verify().is_err());
+
+        ManagedLayout::create_from_template("unknown-layout", b"{ \"type\": \"con\" }").unwrap();
+        assert!(project.verify().is_ok());
+    })
+}
+
+#[test]
+fn warnings_placeholder_count() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          layout = '{ "nodes": [ { "swallows": [ { "class": "^URxvt$" } ] } ] }'
+
+                          [[applications]]
+                          command = "urxvt"
+
+                          [[applications]]
+                          command = "urxvt -e htop""#;
+        let project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+
+        assert!(project.verify().is_ok());
+        assert_eq!(
+            project.warnings(),
+            vec!["the layout contains 1 placeholder(s), but 2 application(s) are started"]
+        );
+    })
+}
+
+#[test]
+fn warnings_shell_syntax() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          on_start = "docker compose up -d && sleep 1"
+
+                          [[applications]]
+                          command = "make > build.log"
+
+                          [[applications]]
+                          command = "tail -f build.log | grep error"
+                          shell = true
+
+                          [[applications]]
+                          command = ["echo", "|"]
+
+                          [[applications]]
+                          command = "grep -E a|b"
+
+                          [[applications]]
+                          command = "sort >out"
+
+                          [[applications]]
+                          command = "env \"$HOME\""
+
+                          [[applications]]
+                          command = "ls *.txt"
+
+                          [[applications]]
+                          command = "cat ~/.bashrc"
+
+                          [[applications]]
+                          command = "printf '$HOME|*.txt' a~b""#;
+        let project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+
+        assert!(project.verify().is_ok());
+        let warning = |name: &str, syntax: &str| {
+            format!(
+                "application '{}' contains shell syntax ('{}') but isn't run through a shell, set \
+                 `shell = true`",
+                name, syntax
+            )
+        };
+        assert_eq!(
+            project.warnings(),
+            vec![
+                "on_start hook 'docker compose up -d && sleep 1' contains shell syntax ('&&') but \
+                 isn't run through a shell, set `shell = true`"
+                    .to_owned(),
+                warning("make", ">"),
+                warning("grep", "a|b"),
+                warning("sort", ">out"),
+                warning("env", "\"$HOME\""),
+                warning("ls", "*.txt"),
+                warning("cat", "~/.bashrc"),
+            ]
+        );
+    })
+}
+
+#[test]
+fn warnings_exec_title() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [[applications]]
+                          command = "urxvt"
+                          window_timeout = 0
+                          exec = { commands = ["ls"], wait_for = { title = '\$ $' } }"#;
+        let project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+
+        assert!(project.verify().is_ok());
+        assert_eq!(
+            project.warnings(),
+            vec![
+                "application 'urxvt' waits for its window title to match '\\$ $', but not for \
+                 its window, its commands won't be input"
+            ]
+        );
+    })
+}
+
+#[test]
+fn warnings_unmatchable_criteria() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          layout = '''
+                          { "swallows": [ { "class": "^Gnome-terminal$", "window_role": "^editor$" } ] }
+                          { "swallows": [ { "class": "^Firefox$" } ] }
+                          { "swallows": [ { "instance": "^my-term$" }, { "class": "^URxvt$" } ] }
+                          { "swallows": [ { "class": "^Code.*$" } ] }
+                          '''
+
+                          [[applications]]
+                          command = "gnome-terminal"
+
+                          [[applications]]
+                          command = "chromium"
+
+                          [[applications]]
+                          command = "urxvt -name my-term"
+
+                          [[applications]]
+                          command = "code""#;
+        let project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+
+        assert!(project.verify().is_ok());
+        assert_eq!(
+            project.warnings(),
+            vec![
+                "the placeholder on line 1 of the layout likely won't swallow any application: no \
+                 application sets a window role (e.g. using `--role`) to match `window_role = \
+                 \"^editor$\"`",
+                "the placeholder on line 2 of the layout likely won't swallow any application: no \
+                 application resembles the class 'Firefox' (e.g. using `--class`)",
+            ]
+        );
+    })
+}
+
+#[test]
+fn verify_workspaces() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [[applications]]
+                          command = "urxvt"
+
+                          [[workspaces]]
+                          workspace = "2"
+                          layout = '{ "swallows": [ { "class": "^Firefox$" } ] }'
+
+                          [[workspaces]]
+                          workspace = "3"
+                          layout = '{ "type": "con", }'"#;
+        let project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+        assert!(project.verify().is_err());
+
+        let template = r#"[general]
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [[applications]]
+                          command = "urxvt"
+
+                          [[workspaces]]
+                          workspace = "2"
+                          layout = '{ "swallows": [ { "class": "^Firefox$" } ] }'"#;
+        let project =
+            Project::create_from_template("project-template-2", template.as_bytes()).unwrap();
+        assert!(project.verify().is_ok());
+        assert_eq!(
+            project.warnings(),
+            vec![
+                "workspace '2': the layout contains 1 placeholder(s), but 0 application(s) are \
+                 started",
+                "workspace '2': the placeholder on line 1 of the layout likely won't swallow any \
+                 application: no application resembles the class 'Firefox' (e.g. using \
+                 `--class`)",
+            ]
+        );
+    })
+}
+
+#[test]
+fn verify_empty() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          workspace = "1"
+
+                          [variables]
+                          branch = "master""#;
+        let project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+        match project.verify() {
+            Err(Error(ErrorKind::EmptyProject(name), _)) => assert_eq!(name, "project-template"),
+            result => panic!("expected empty project, got {:?}", result),
+        }
+
+        // A project consisting only of additional workspaces isn't empty
+        let template = r#"[general]
+
+                          [[workspaces]]
+                          workspace = "2"
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [[workspaces.applications]]
+                          command = "urxvt""#;
+        let project =
+            Project::create_from_template("project-template-2", template.as_bytes()).unwrap();
+        assert!(project.verify().is_ok());
+    })
+}
+
+#[test]
+fn verify_env_files() {
+    with_projects_dir(|_| {
+        let env_dir = TempDir::new("i3nator-env").unwrap();
+        let mut file = File::create(env_dir.path().join(".env")).unwrap();
+        file.write_all(
+            br#"# Comment
+                export FOO=bar # trailing comment
+                QUOTED="multiple \"words\""
+                LITERAL='$HOME'
+                EMPTY=
+            "#,
+        )
+        .unwrap();
+        let mut file = File::create(env_dir.path().join("malformed.env")).unwrap();
+        file.write_all(b"FOO=bar\nQUOTED=\"unterminated\n").unwrap();
+
+        let template = format!(
+            r#"[general]
+               working_directory = "{}"
+               env_file = ".env"
+               layout = '{{ "swallows": [ {{ "class": "^mycommand$" }} ] }}'
+
+               [[applications]]
+               command = "mycommand"
+               env_file = ["{}/.env"]"#,
+            env_dir.path().display(),
+            env_dir.path().display()
+        );
+        let project = Project::create_from_template("project-valid", template.as_bytes()).unwrap();
+        assert!(project.verify().is_ok());
+
+        let template = template.replacen(".env", "missing.env", 1);
+        let project =
+            Project::create_from_template("project-missing", template.as_bytes()).unwrap();
+        match project.verify() {
+            Err(Error(ErrorKind::PathDoesntExist(path), _)) => {
+                assert!(path.ends_with("missing.env"))
+            }
+            result => panic!("expected missing env file, got {:?}", result),
+        }
+
+        let template = template.replacen("missing.env", "malformed.env", 1);
+        let project =
+            Project::create_from_template("project-malformed", template.as_bytes()).unwrap();
+        match project.verify() {
+            Err(Error(ErrorKind::InvalidEnvFile(path, line, _), _)) => {
+                assert!(path.ends_with("malformed.env"));
+                assert_eq!(line, 2);
+            }
+            result => panic!("expected malformed env file, got {:?}", result),
+        }
+    })
+}
+
+#[test]
+fn variables() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          working_directory = "${repository}"
+                          workspace = "${workspace}"
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [variables]
+                          repository = { description = "path to the repository" }
+                          branch = "master"
+                          workspace = "1"
+
+                          [[applications]]
+                          command = "urxvt -e git checkout ${branch}"
+                          exec = "echo ${branch} ${UNDECLARED} $HOME""#;
+        let mut project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+
+        match project.verify() {
+            Err(Error(ErrorKind::UndefinedVariable(name, description), _)) => {
+                assert_eq!(name, "repository");
+                assert_eq!(description, Some("path to the repository".to_owned()));
+            }
+            result => panic!("expected undefined variable, got {:?}", result),
+        }
+
+        project.set_variable("repository", "/tmp");
+        project.set_variable("branch", "feature");
+        assert!(project.verify().is_ok());
+
+        let config = project.config().unwrap();
+        assert_eq!(config.general.working_directory, Some("/tmp".into()));
+        assert_eq!(config.general.workspace, Some("1".to_owned()));
+        assert_eq!(
+            config.applications[0].command.args,
+            vec!["-e", "git", "checkout", "feature"]
+        );
+        assert_eq!(
+            config.applications[0].exec.as_ref().unwrap().commands,
+            vec!["echo feature ${UNDECLARED} $HOME"]
+        );
+
+        project.set_variable("unknown", "value");
+        match project.verify() {
+            Err(Error(ErrorKind::UnknownVariable(name), _)) => assert_eq!(name, "unknown"),
+            result => panic!("expected unknown variable, got {:?}", result),
+        }
+    })
+}
+
+#[test]
+fn variables_with_whitespace() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+                          on_start = { commands = ["notify-send ${message}"], timeout = 5 }
+
+                          [variables]
+                          message = "hello world"
+
+                          [[applications]]
+                          command = "urxvt -title ${message} -e echo '${message}'"
+
+                          [[applications]]
+                          command = ["urxvt", "-title", "${message}"]
+
+                          [[applications]]
+                          command = "echo ${message} | cat"
+                          shell = true
+
+                          [[applications]]
+                          command = "echo \"${message}\" '${message}' \\${message}"
+                          shell = true"#;
+        let mut project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+        assert!(project.verify().is_ok());
+
+        let config = project.config().unwrap();
+        let hooks = config.general.on_start.as_ref().unwrap();
+        assert_eq!(hooks.commands[0].args, vec!["hello world"]);
+        assert_eq!(hooks.timeout, Duration::from_secs(5));
+        assert_eq!(
+            config.applications[0].command.args,
+            vec!["-title", "hello world", "-e", "echo", "hello world"]
+        );
+        assert_eq!(
+            config.applications[1].command.args,
+            vec!["-title", "hello world"]
+        );
+        // Commands run through a shell are passed to it as specified, with the values quoted
+        assert_eq!(
+            config.applications[2].command.commandline,
+            Some("echo 'hello world' | cat".to_owned())
+        );
+        assert_eq!(
+            config.applications[3].command.commandline,
+            Some(r#"echo "hello world" 'hello world' \${message}"#.to_owned())
+        );
+
+        // Values can't change the meaning of shell commands
+        project.set_variable("message", "it's $(date)");
+        let config = project.config().unwrap();
+        assert_eq!(
+            config.applications[2].command.commandline,
+            Some(r#"echo 'it'\''s $(date)' | cat"#.to_owned())
+        );
+        assert_eq!(
+            config.applications[3].command.commandline,
+            Some(r#"echo "it's \$(date)" 'it'\''s $(date)' \${message}"#.to_owned())
+        );
+    })
+}
+
+#[test]
+fn extends() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          working_directory = "/tmp"
+                          workspace = "1"
+                          env = { RUST_LOG = "debug", RUST_BACKTRACE = "1" }
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [[applications]]
+                          command = "urxvt""#;
+        Project::create_from_template("base", template.as_bytes()).unwrap();
+
+        let template = r#"[general]
+                          extends = "base"
+                          workspace = "2"
+                          env = { RUST_LOG = "info" }
+
+                          [[applications]]
+                          command = "urxvt -e htop""#;
+        let mut project =
+            Project::create_from_template("project-append", template.as_bytes()).unwrap();
+        assert!(project.verify().is_ok());
+
+        let config = project.config().unwrap();
+        assert_eq!(config.general.working_directory, Some("/tmp".into()));
+        assert_eq!(config.general.workspace, Some("2".to_owned()));
+        assert_eq!(
+            config.general.env,
+            vec![
+                ("RUST_BACKTRACE".to_owned(), "1".to_owned()),
+                ("RUST_LOG".to_owned(), "info".to_owned()),
+            ]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>()
+        );
+        assert_eq!(
+            config
+                .applications
+                .iter()
+                .map(|application| application.command.program.as_str())
+                .collect::<Vec<_>>(),
+            vec!["urxvt", "urxvt"]
+        );
+        assert_eq!(config.applications[1].command.args, vec!["-e", "htop"]);
+
+        let template = r#"[general]
+                          extends = { name = "project-append", applications = "replace" }
+
+                          [[applications]]
+                          command = "urxvt -e top""#;
+        let mut project =
+            Project::create_from_template("project-replace", template.as_bytes()).unwrap();
+        assert!(project.verify().is_ok());
+
+        let config = project.config().unwrap();
+        assert_eq!(config.general.workspace, Some("2".to_owned()));
+        assert_eq!(config.applications.len(), 1);
+        assert_eq!(config.applications[0].command.args, vec!["-e", "top"]);
+    })
+}
+
+#[test]
+fn extends_include() {
+    with_projects_dir(|_| {
+        Fragment::create_from_template(
+            "fragment-htop",
+            b"[[applications]]\ncommand = \"urxvt -e htop\"\n",
+        )
+        .unwrap();
+
+        let template = r#"[general]
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [[applications]]
+                          command = "urxvt"
+
+                          [[include]]
+                          path = "fragment-htop""#;
+        Project::create_from_template("base", template.as_bytes()).unwrap();
+
+        // Replacing the applications keeps the included ones
+        let template = r#"[general]
+                          extends = { name = "base", applications = "replace" }
+
+                          [[applications]]
+                          command = "urxvt -e top""#;
+        let mut project =
+            Project::create_from_template("project-replace", template.as_bytes()).unwrap();
+        assert!(project.verify().is_ok());
+
+        let config = project.config().unwrap();
+        assert_eq!(
+            config.include,
+            vec![Include {
+                path: types::Fragment::Managed("fragment-htop".to_owned()),
+            }]
+        );
+        assert_eq!(
+            config
+                .applications
+                .iter()
+                .map(|application| application.command.args.join(" "))
+                .collect::<Vec<_>>(),
+            vec!["-e top", "-e htop"]
+        );
+    })
+}
+
+#[test]
+fn extends_invalid() {
+    with_projects_dir(|_| {
+        let project =
+            Project::create_from_template("project-a", b"[general]\nextends = \"project-b\"\n")
+                .unwrap();
+        Project::create_from_template("project-b", b"[general]\nextends = \"project-a\"\n")
+            .unwrap();
+        match project.verify() {
+            Err(Error(ErrorKind::CyclicExtends(chain), _)) => {
+                assert_eq!(chain, "project-a -> project-b -> project-a")
+            }
+            result => panic!("expected cyclic extends, got {:?}", result),
+        }
+
+        let project = Project::create_from_template(
+            "project-unknown",
+            b"[general]\nextends = \"project-missing\"\n",
+        )
+        .unwrap();
+        match project.verify() {
+            Err(Error(ErrorKind::UnknownConfig(_, name), _)) => {
+                assert_eq!(name, "project-missing")
+            }
+            result => panic!("expected unknown config, got {:?}", result),
+        }
+    })
+}
+
+#[test]
+fn include() {
+    with_projects_dir(|projects_dir| {
+        let fragment = Fragment::create_from_template(
+            "fragment-htop",
+            b"[[applications]]\ncommand = \"urxvt -e htop\"\n",
+        )
+        .unwrap();
+        assert!(fragment.verify().is_ok());
+        assert_eq!(fragments::list(), vec![OsString::from("fragment-htop")]);
+
+        let fragments_dir = TempDir::new("i3nator-tests-fragments").unwrap();
+        let top = fragments_dir.path().join("top.toml");
+        let mut file = File::create(&top).unwrap();
+        file.write_all(b"[[applications]]\ncommand = \"urxvt -e top -d ${delay}\"\n")
+            .unwrap();
+
+        let template = format!(
+            r#"[general]
+               layout = '{{ "swallows": [ {{ "class": "^URxvt$" }} ] }}'
+
+               [variables]
+               delay = "5"
+
+               [[applications]]
+               command = "urxvt"
+
+               [[include]]
+               path = "fragment-htop"
+
+               [[include]]
+               path = "{}""#,
+            top.display()
+        );
+        let mut project =
+            Project::create_from_template("project-include", template.as_bytes()).unwrap();
+        assert!(project.verify().is_ok());
+
+        let config = project.config().unwrap();
+        assert_eq!(
+            config.include,
+            vec![
+                Include {
+                    path: types::Fragment::Managed("fragment-htop".to_owned()),
+                },
+                Include {
+                    path: types::Fragment::Path(top.clone()),
+                },
+            ]
+        );
+        assert_eq!(
+            config
+                .applications
+                .iter()
+                .map(|application| application.command.args.join(" "))
+                .collect::<Vec<_>>(),
+            vec!["", "-e htop", "-e top -d 5"]
+        );
+
+        // The variables of the including project are interpolated in the fragment
+        project.set_variable("delay", "1");
+        assert_eq!(
+            project.config().unwrap().applications[2].command.args,
+            vec!["-e", "top", "-d", "1"]
+        );
+
+        let template = r#"[general]
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [[include]]
+                          path = "missing.toml""#;
+        let project =
+            Project::create_from_template("project-include-missing", template.as_bytes()).unwrap();
+        match project.verify() {
+            Err(Error(ErrorKind::PathDoesntExist(path), _)) => {
+                assert_eq!(path, projects_dir.join("missing.toml").to_string_lossy())
+            }
+            result => panic!("expected missing path, got {:?}", result),
+        }
+    })
+}
+
+#[test]
+fn dependencies() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [[applications]]
+                          name = "terminal"
+                          command = "urxvt"
+                          depends_on = ["server"]
+
+                          [[applications]]
+                          name = "server"
+                          command = "python3 -m http.server 8080"
+                          wait_for_window = false
+                          wait_for_port = 8080
+
+                          [[applications]]
+                          command = "htop"
+
+                          [[workspaces]]
+                          workspace = "2"
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [[workspaces.applications]]
+                          command = "urxvt -e tail -f server.log"
+                          depends_on = ["server", "htop"]"#;
+        let mut project =
+            Project::create_from_template("project-dependencies", template.as_bytes()).unwrap();
+        assert!(project.verify().is_ok());
+        assert_eq!(
+            project
+                .start_order()
+                .unwrap()
+                .iter()
+                .map(|application| application.command.program.as_str())
+                .collect::<Vec<_>>(),
+            vec!["python3", "urxvt", "htop", "urxvt"]
+        );
+
+        let invalid = vec![
+            (
+                r#"[[applications]]
+                   name = "a"
+                   command = "a"
+                   depends_on = ["b"]
+
+                   [[applications]]
+                   name = "b"
+                   command = "b"
+                   depends_on = ["a"]"#,
+                "a",
+                "cyclic dependency between 'a', 'b'",
+            ),
+            (
+                r#"[[applications]]
+                   command = "a"
+                   depends_on = ["unknown"]"#,
+                "a",
+                "there is no application named 'unknown'",
+            ),
+            (
+                r#"[[applications]]
+                   command = "a"
+                   depends_on = ["b"]
+
+                   [[workspaces]]
+                   workspace = "2"
+                   layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                   [[workspaces.applications]]
+                   name = "b"
+                   command = "b""#,
+                "a",
+                "'b' is started on a later workspace",
+            ),
+        ];
+        for (applications, expected_application, expected_error) in invalid {
+            let template = format!(
+                "[general]\nlayout = '{{ \"swallows\": [ {{ \"class\": \"^URxvt$\" }} ] }}'\n{}",
+                applications
+            );
+            let project =
+                Project::create_from_template("project-invalid-dependencies", template.as_bytes())
+                    .unwrap();
+            match project.verify() {
+                Err(Error(ErrorKind::InvalidDependency(application, error), _)) => {
+                    assert_eq!(application, expected_application);
+                    assert_eq!(error, expected_error);
+                }
+                result => panic!("expected invalid dependency, got {:?}", result),
+            }
+            project.delete().unwrap();
+        }
+    })
+}
+
+#[test]
+fn exec_target() {
+    with_projects_dir(|_| {
+        let template = r#"[general]
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [[applications]]
+                          command = "urxvt -e tmux new-session -s editor"
+                          exec = { commands = ["vim"], backend = "tmux", target = "editor" }"#;
+        let project =
+            Project::create_from_template("project-exec-target", template.as_bytes()).unwrap();
+        assert!(project.verify().is_ok());
+
+        let template = r#"[general]
+                          layout = '{ "swallows": [ { "class": "^URxvt$" } ] }'
+
+                          [[applications]]
+                          command = "kitty"
+                          exec = { commands = ["vim"], backend = "kitty" }"#;
+        let project =
+            Project::create_from_template("project-exec-target-missing", template.as_bytes())
+                .unwrap();
+        match project.verify() {
+            Err(Error(ErrorKind::ExecTargetMissing(backend), _)) => assert_eq!(backend, "kitty"),
+            result => panic!("expected missing exec target, got {:?}", result),
+        }
+
+        let template = r#"[general]
+                          layout = '{ "swallows": [ { "class": "^org.wezfurlong.wezterm$" } ] }'
+
+                          [[applications]]
+                          command = "wezterm"
+                          exec = { commands = ["vim"], backend = "wezterm" }"#;
+        let project = Project::create_from_template(
+            "project-exec-target-missing-wezterm",
+            template.as_bytes(),
+        )
+        .unwrap();
+        match project.verify() {
+            Err(Error(ErrorKind::ExecTargetMissing(backend), _)) => {
+                assert_eq!(backend, "wezterm")
+            }
+            result => panic!("expected missing exec target, got {:?}", result),
+        }
+    })
+}
+
+fn window_node(id: i64, name: &str, nodetype: NodeType, nodes: Vec<Node>) -> Node {
+    let window = if nodetype == NodeType::Con {
+        Some(id as i32)
+    } else {
+        None
+    };
+    Node {
+        focus: vec![],
+        nodes,
+        floating_nodes: vec![],
+        id,
+        name: Some(name.to_owned()),
+        nodetype,
+        border: NodeBorder::Normal,
+        current_border_width: 2,
+        layout: NodeLayout::SplitH,
+        percent: None,
+        rect: (0, 0, 800, 600),
+        window_rect: (0, 0, 0, 0),
+        deco_rect: (0, 0, 0, 0),
+        geometry: (0, 0, 0, 0),
+        window,
+        window_properties: Some(
+            vec![(WindowProperty::Class, name.to_owned())]
+                .into_iter()
+                .collect(),
+        ),
+        urgent: false,
+        focused: false,
+    }
+}
+
+#[test]
+fn snapshot() {
+    with_projects_dir(|_| {
+        let workspace = window_node(
+            1,
+            "dev",
+            NodeType::Workspace,
+            vec![
+                window_node(2, "this", NodeType::Con, vec![]),
+                window_node(3, "other", NodeType::Con, vec![]),
+            ],
+        );
+
+        // The first window belongs to the process running the tests, the owner of the second one
+        // can't be determined.
+        let snapshot = projects::workspace_project(&workspace, |window| match window {
+            2 => Some(std::process::id()),
+            _ => None,
+        })
+        .unwrap();
+        assert!(
+            snapshot.contains("# The command for the window \"other\" could not be determined.")
+        );
+
+        let config: Config = toml::from_str(&snapshot).unwrap();
+        let working_directory = env::current_dir().unwrap();
+        let args = env::args().collect::<Vec<_>>();
+        assert_eq!(config.general.workspace, Some("dev".to_owned()));
+        assert_eq!(config.general.working_directory, Some(working_directory));
+        match config.general.layout {
+            Some(Layout::Contents(ref layout)) => {
+                assert!(layout.contains(r#""class": "^this$""#));
+                assert!(layout.contains(r#""class": "^other$""#));
+            }
+            ref layout => panic!("expected layout contents, got {:?}", layout),
+        }
+        assert_eq!(config.applications.len(), 1);
+        assert_eq!(config.applications[0].command.program, args[0]);
+        assert_eq!(config.applications[0].command.args, &args[1..]);
+        assert_eq!(config.applications[0].working_directory, None);
+    })
+}
+
+/// Connect to a socket that never answers, standing in for i3. Starting a project only talks to i3
+/// after the `on_start` hooks succeeded.
+fn unresponsive_i3(projects_dir: &Path) -> (UnixListener, I3Connection) {
+    let socket = projects_dir.join("i3.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+    env::set_var("I3SOCK", &socket);
+    let i3 = I3Connection::connect().unwrap();
+    env::remove_var("I3SOCK");
+    (listener, i3)
+}
+
+#[test]
+fn start_on_start_hook_failed() {
+    with_projects_dir(|projects_dir| {
+        let marker = projects_dir.join("marker");
+        let template = format!(
+            r#"[general]
+               on_start = ["false", ["touch", "{}"]]
+
+               [[applications]]
+               command = "urxvt""#,
+            marker.display()
+        );
+        let mut project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+        let (_listener, mut i3) = unresponsive_i3(projects_dir);
+
+        match project.start(&mut i3, None, None, Selection::All) {
+            Err(Error(ErrorKind::HookFailed(command, _), _)) => assert_eq!(command, "false"),
+            other => panic!("expected HookFailed, got {:?}", other),
+        }
+        // The remaining hooks aren't run either
+        assert!(!marker.exists());
+    })
+}
+
+#[test]
+fn start_on_start_hook_timeout() {
+    with_projects_dir(|projects_dir| {
+        let marker = projects_dir.join("marker");
+        let template = format!(
+            r#"[general]
+               on_start = {{ commands = [["sh", "-c", "sleep 2 && touch {}"]], timeout = 1 }}
+
+               [[applications]]
+               command = "urxvt""#,
+            marker.display()
+        );
+        let mut project =
+            Project::create_from_template("project-template", template.as_bytes()).unwrap();
+        let (_listener, mut i3) = unresponsive_i3(projects_dir);
+
+        let start = Instant::now();
+        match project.start(&mut i3, None, None, Selection::All) {
+            Err(Error(ErrorKind::HookFailed(_, reason), _)) => {
+                assert_eq!(reason, "didn't finish within 1s")
+            }
+            other => panic!("expected HookFailed, got {:?}", other),
+        }
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // The hook was killed rather than left running in the background
+        thread::sleep(Duration::from_millis(1500));
+        assert!(!marker.exists());
+    })
+}
+
 #[test]
 fn copy() {
     with_projects_dir(|projects_dir| {
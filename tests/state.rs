@@ -0,0 +1,154 @@
+// Copyright Pit Kleyersburg <pitkley@googlemail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified or distributed
+// except according to those terms.
+
+#![cfg(feature = "sequential-tests")]
+
+use i3nator::state::{self, ApplicationState, ProjectState};
+use lazy_static::lazy_static;
+use std::{
+    env, fs,
+    os::unix::fs::PermissionsExt,
+    panic::{self, UnwindSafe},
+    path::{Path, PathBuf},
+    process,
+};
+use tempdir::TempDir;
+
+lazy_static! {
+    static ref TMP_DIR: TempDir = TempDir::new("i3nator-tests").unwrap();
+    static ref STATE_DIR: PathBuf = TMP_DIR.path().join("i3nator/projects");
+}
+
+fn with_runtime_dir<F: FnOnce(&Path)>(body: F)
+where
+    F: UnwindSafe,
+{
+    // Set up temporary XDG runtime directory, which is required to be only accessible by the user
+    fs::set_permissions(TMP_DIR.path(), fs::Permissions::from_mode(0o700))
+        .expect("couldn't set permissions of temporary directory");
+    env::set_var("XDG_RUNTIME_DIR", TMP_DIR.path());
+
+    // Run body
+    let panic_result = panic::catch_unwind(|| body(STATE_DIR.as_ref()));
+
+    // Remove the recorded states
+    if STATE_DIR.exists() {
+        fs::remove_dir_all(&*STATE_DIR).expect("couldn't delete temporary directories");
+    }
+
+    if let Err(err) = panic_result {
+        panic::resume_unwind(err);
+    }
+}
+
+#[test]
+fn load_unknown() {
+    with_runtime_dir(|_| {
+        assert_eq!(ProjectState::load("unknown-project").unwrap(), None);
+    })
+}
+
+#[test]
+fn save_load_delete() {
+    with_runtime_dir(|state_dir| {
+        assert!(state::is_available());
+
+        let mut state = ProjectState::new("project");
+        state.workspaces = vec!["1".to_owned(), "2".to_owned()];
+        state.containers = vec![1, 2, 3];
+        state.applications = vec![ApplicationState {
+            program: "mycommand".to_owned(),
+            pid: process::id(),
+            start_time: state::process_start_time(process::id()),
+            container: Some(4),
+        }];
+        state.save().unwrap();
+        assert!(state_dir.join("project.toml").exists());
+
+        let loaded = ProjectState::load("project").unwrap().unwrap();
+        assert_eq!(loaded, state);
+        assert_eq!(loaded.alive_pids(), vec![process::id()]);
+        assert!(loaded.is_running());
+        let listed = ProjectState::list();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].0, "project");
+        assert_eq!(listed[0].1.as_ref().unwrap(), &loaded);
+
+        loaded.delete().unwrap();
+        assert!(!state_dir.join("project.toml").exists());
+        assert_eq!(ProjectState::load("project").unwrap(), None);
+        assert!(ProjectState::list().is_empty());
+    })
+}
+
+#[test]
+fn list_corrupt() {
+    with_runtime_dir(|state_dir| {
+        ProjectState::new("project-a").save().unwrap();
+        fs::write(
+            state_dir.join("project-b.toml"),
+            b"name = \"project-b\"\nworkspaces = [",
+        )
+        .unwrap();
+        ProjectState::new("project-c").save().unwrap();
+
+        // A state that can't be parsed doesn't prevent the others from being listed
+        let listed = ProjectState::list();
+        assert_eq!(
+            listed
+                .iter()
+                .map(|(name, state)| (name.as_str(), state.is_ok()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("project-a", true),
+                ("project-b", false),
+                ("project-c", true)
+            ]
+        );
+    })
+}
+
+#[test]
+fn not_running() {
+    with_runtime_dir(|_| {
+        let mut state = ProjectState::new("project");
+        state.applications = vec![ApplicationState {
+            program: "mycommand".to_owned(),
+            // PIDs are limited to 2^22 on Linux, so this process can not exist.
+            pid: u32::MAX,
+            start_time: Some(0),
+            container: None,
+        }];
+
+        assert!(!state.is_running());
+        assert!(state.alive_pids().is_empty());
+    })
+}
+
+#[test]
+fn pid_reused() {
+    with_runtime_dir(|_| {
+        let start_time = state::process_start_time(process::id()).unwrap();
+        assert!(state::is_process_alive(process::id(), start_time));
+
+        // A process with the same PID but a different start time is another process
+        let mut state = ProjectState::new("project");
+        state.applications = vec![ApplicationState {
+            program: "mycommand".to_owned(),
+            pid: process::id(),
+            start_time: Some(start_time + 1),
+            container: None,
+        }];
+        assert!(!state.is_running());
+        assert!(state.alive_pids().is_empty());
+
+        // Without a start time the process can't be identified
+        state.applications[0].start_time = None;
+        assert!(!state.is_running());
+    })
+}
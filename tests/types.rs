@@ -7,7 +7,7 @@
 // except according to those terms.
 
 use i3nator::types::*;
-use std::time::Duration;
+use std::{collections::BTreeMap, env, path::PathBuf, time::Duration};
 
 macro_rules! equivalent {
     ( $fragment:expr, $expected:expr; $ty:ty ) => {
@@ -22,20 +22,49 @@ fn full_config() {
         general: General {
             working_directory: Some("/path/to/my/working/directory".to_owned().into()),
             workspace: Some("0".to_owned()),
-            layout: Layout::Path("/path/to/my/layout.json".into()),
+            layout: Some(Layout::Path("/path/to/my/layout.json".into())),
+            output: vec![],
+            env: BTreeMap::new(),
+            unset_env: vec![],
+            env_file: vec![],
+            extends: None,
+            on_start: None,
+            after_start: None,
+            on_stop: None,
         },
         applications: vec![Application {
             command: ApplicationCommand {
                 program: "mycommand".to_owned(),
                 args: vec!["--with".to_owned(), "multiple args".to_owned()],
+                commandline: None,
             },
             working_directory: Some("/path/to/a/different/working/directory".to_owned().into()),
             exec: Some(Exec {
                 commands: vec!["command one".to_owned(), "command two".to_owned()],
+                steps: vec![],
                 exec_type: ExecType::TextNoReturn,
+                backend: ExecBackend::X11,
+                target: None,
+                wait_for: WaitFor::default(),
+                paste: Paste::default(),
                 timeout: Duration::from_secs(5),
             }),
+            window_timeout: Duration::from_secs(5),
+            env: BTreeMap::new(),
+            unset_env: vec![],
+            env_file: vec![],
+            name: None,
+            depends_on: vec![],
+            wait_for_window: true,
+            wait_for_port: None,
+            wait_for_file: None,
+            ready_timeout: Duration::from_secs(30),
+            delay: Duration::from_secs(0),
+            shell: None,
         }],
+        workspaces: vec![],
+        variables: BTreeMap::new(),
+        include: vec![],
     };
 
     equivalent! {
@@ -55,15 +84,172 @@ fn full_config() {
     }
 }
 
+#[test]
+fn workspaces_only() {
+    let expected = Config {
+        general: General {
+            working_directory: None,
+            workspace: None,
+            layout: None,
+            output: vec![],
+            env: BTreeMap::new(),
+            unset_env: vec![],
+            env_file: vec![],
+            extends: None,
+            on_start: None,
+            after_start: None,
+            on_stop: None,
+        },
+        applications: vec![],
+        workspaces: vec![
+            Workspace {
+                workspace: "1".to_owned(),
+                layout: Layout::Path("/path/to/my/layout.json".into()),
+                applications: vec![Application {
+                    command: ApplicationCommand {
+                        program: "mycommand".to_owned(),
+                        args: vec![],
+                        commandline: None,
+                    },
+                    working_directory: None,
+                    exec: None,
+                    window_timeout: Duration::from_secs(5),
+                    env: BTreeMap::new(),
+                    unset_env: vec![],
+                    env_file: vec![],
+                    name: None,
+                    depends_on: vec![],
+                    wait_for_window: true,
+                    wait_for_port: None,
+                    wait_for_file: None,
+                    ready_timeout: Duration::from_secs(30),
+                    delay: Duration::from_secs(0),
+                    shell: None,
+                }],
+                output: vec![],
+            },
+            Workspace {
+                workspace: "2".to_owned(),
+                layout: Layout::Contents("{ ... }".to_owned()),
+                applications: vec![],
+                output: vec![],
+            },
+        ],
+        variables: BTreeMap::new(),
+        include: vec![],
+    };
+
+    equivalent! {
+        r#"
+            [general]
+
+            [[workspaces]]
+            workspace = "1"
+            layout = "/path/to/my/layout.json"
+
+            [[workspaces.applications]]
+            command = "mycommand"
+
+            [[workspaces]]
+            workspace = "2"
+            layout = "{ ... }"
+        "#,
+        expected;
+        Config
+    }
+}
+
+#[test]
+fn workspace_output_str() {
+    let expected = Workspace {
+        workspace: "1".to_owned(),
+        layout: Layout::Contents("{ ... }".to_owned()),
+        applications: vec![],
+        output: vec!["DP-1".to_owned(), "HDMI-1".to_owned(), "primary".to_owned()],
+    };
+
+    equivalent! {
+        r#"
+            workspace = "1"
+            layout = "{ ... }"
+            output = "DP-1  HDMI-1 primary"
+        "#,
+        expected;
+        Workspace
+    }
+}
+
+#[test]
+fn workspace_output_seq() {
+    let expected = Workspace {
+        workspace: "1".to_owned(),
+        layout: Layout::Contents("{ ... }".to_owned()),
+        applications: vec![],
+        output: vec!["DP-1".to_owned(), "primary".to_owned()],
+    };
+
+    equivalent! {
+        r#"
+            workspace = "1"
+            layout = "{ ... }"
+            output = ["DP-1", "primary"]
+        "#,
+        expected;
+        Workspace
+    }
+}
+
+#[test]
+fn variables() {
+    let config = toml::from_str::<Config>(
+        r#"
+            [general]
+
+            [variables]
+            branch = "master"
+            repository = { description = "path to the repository" }
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.variables.get("branch"),
+        Some(&Variable {
+            default: Some("master".to_owned()),
+            description: None,
+        })
+    );
+    assert_eq!(
+        config.variables.get("repository"),
+        Some(&Variable {
+            default: None,
+            description: Some("path to the repository".to_owned()),
+        })
+    );
+}
+
 #[test]
 fn application_command_str() {
     let expected = Application {
         command: ApplicationCommand {
             program: "mycommand".to_owned(),
             args: vec!["--with".to_owned(), "multiple args".to_owned()],
+            commandline: None,
         },
         working_directory: None,
         exec: None,
+        window_timeout: Duration::from_secs(5),
+        env: BTreeMap::new(),
+        unset_env: vec![],
+        env_file: vec![],
+        name: None,
+        depends_on: vec![],
+        wait_for_window: true,
+        wait_for_port: None,
+        wait_for_file: None,
+        ready_timeout: Duration::from_secs(30),
+        delay: Duration::from_secs(0),
+        shell: None,
     };
 
     equivalent! {
@@ -79,9 +265,22 @@ fn application_command_str_no_args() {
         command: ApplicationCommand {
             program: "mycommand".to_owned(),
             args: vec![],
+            commandline: None,
         },
         working_directory: None,
         exec: None,
+        window_timeout: Duration::from_secs(5),
+        env: BTreeMap::new(),
+        unset_env: vec![],
+        env_file: vec![],
+        name: None,
+        depends_on: vec![],
+        wait_for_window: true,
+        wait_for_port: None,
+        wait_for_file: None,
+        ready_timeout: Duration::from_secs(30),
+        delay: Duration::from_secs(0),
+        shell: None,
     };
 
     equivalent! {
@@ -103,9 +302,22 @@ fn application_command_seq() {
         command: ApplicationCommand {
             program: "mycommand".to_owned(),
             args: vec!["--with".to_owned(), "multiple args".to_owned()],
+            commandline: None,
         },
         working_directory: None,
         exec: None,
+        window_timeout: Duration::from_secs(5),
+        env: BTreeMap::new(),
+        unset_env: vec![],
+        env_file: vec![],
+        name: None,
+        depends_on: vec![],
+        wait_for_window: true,
+        wait_for_port: None,
+        wait_for_file: None,
+        ready_timeout: Duration::from_secs(30),
+        delay: Duration::from_secs(0),
+        shell: None,
     };
 
     equivalent! {
@@ -121,9 +333,22 @@ fn application_command_seq_no_args() {
         command: ApplicationCommand {
             program: "mycommand".to_owned(),
             args: vec![],
+            commandline: None,
         },
         working_directory: None,
         exec: None,
+        window_timeout: Duration::from_secs(5),
+        env: BTreeMap::new(),
+        unset_env: vec![],
+        env_file: vec![],
+        name: None,
+        depends_on: vec![],
+        wait_for_window: true,
+        wait_for_port: None,
+        wait_for_file: None,
+        ready_timeout: Duration::from_secs(30),
+        delay: Duration::from_secs(0),
+        shell: None,
     };
 
     equivalent! {
@@ -145,9 +370,22 @@ fn application_command_map() {
         command: ApplicationCommand {
             program: "mycommand".to_owned(),
             args: vec!["--with".to_owned(), "multiple args".to_owned()],
+            commandline: None,
         },
         working_directory: None,
         exec: None,
+        window_timeout: Duration::from_secs(5),
+        env: BTreeMap::new(),
+        unset_env: vec![],
+        env_file: vec![],
+        name: None,
+        depends_on: vec![],
+        wait_for_window: true,
+        wait_for_port: None,
+        wait_for_file: None,
+        ready_timeout: Duration::from_secs(30),
+        delay: Duration::from_secs(0),
+        shell: None,
     };
 
     equivalent! {
@@ -163,9 +401,22 @@ fn application_command_map_no_args() {
         command: ApplicationCommand {
             program: "mycommand".to_owned(),
             args: vec![],
+            commandline: None,
         },
         working_directory: None,
         exec: None,
+        window_timeout: Duration::from_secs(5),
+        env: BTreeMap::new(),
+        unset_env: vec![],
+        env_file: vec![],
+        name: None,
+        depends_on: vec![],
+        wait_for_window: true,
+        wait_for_port: None,
+        wait_for_file: None,
+        ready_timeout: Duration::from_secs(30),
+        delay: Duration::from_secs(0),
+        shell: None,
     };
 
     equivalent! {
@@ -175,6 +426,371 @@ fn application_command_map_no_args() {
     }
 }
 
+#[test]
+fn application_window_timeout() {
+    let expected = Application {
+        command: ApplicationCommand {
+            program: "mycommand".to_owned(),
+            args: vec![],
+            commandline: None,
+        },
+        working_directory: None,
+        exec: None,
+        window_timeout: Duration::from_secs(0),
+        env: BTreeMap::new(),
+        unset_env: vec![],
+        env_file: vec![],
+        name: None,
+        depends_on: vec![],
+        wait_for_window: true,
+        wait_for_port: None,
+        wait_for_file: None,
+        ready_timeout: Duration::from_secs(30),
+        delay: Duration::from_secs(0),
+        shell: None,
+    };
+
+    equivalent! {
+        r#"command = "mycommand"
+           window_timeout = 0"#,
+        expected;
+        Application
+    }
+}
+
+#[test]
+fn application_env() {
+    let mut env = BTreeMap::new();
+    env.insert("RUST_LOG".to_owned(), "debug".to_owned());
+    env.insert("EMPTY".to_owned(), "".to_owned());
+
+    let expected = Application {
+        command: ApplicationCommand {
+            program: "mycommand".to_owned(),
+            args: vec![],
+            commandline: None,
+        },
+        working_directory: None,
+        exec: None,
+        window_timeout: Duration::from_secs(5),
+        env,
+        unset_env: vec!["DISPLAY".to_owned()],
+        env_file: vec![],
+        name: None,
+        depends_on: vec![],
+        wait_for_window: true,
+        wait_for_port: None,
+        wait_for_file: None,
+        ready_timeout: Duration::from_secs(30),
+        delay: Duration::from_secs(0),
+        shell: None,
+    };
+
+    equivalent! {
+        r#"command = "mycommand"
+           env = { RUST_LOG = "debug", EMPTY = "" }
+           unset_env = ["DISPLAY"]"#,
+        expected;
+        Application
+    }
+}
+
+#[test]
+fn application_env_file() {
+    let application = toml::from_str::<Application>(
+        r#"command = "mycommand"
+           env_file = ".env""#,
+    )
+    .unwrap();
+    assert_eq!(application.env_file, vec![PathBuf::from(".env")]);
+
+    let application = toml::from_str::<Application>(
+        r#"command = "mycommand"
+           env_file = ["~/.env", "/path/to/.env"]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        application.env_file,
+        vec![
+            dirs_next::home_dir().unwrap().join(".env"),
+            PathBuf::from("/path/to/.env")
+        ]
+    );
+}
+
+#[test]
+fn path_expansion() {
+    env::set_var("I3NATOR_TEST_PATH", "/path/to");
+
+    let application = toml::from_str::<Application>(
+        r#"command = "mycommand"
+           working_directory = "$I3NATOR_TEST_PATH/src"
+           env_file = ["${I3NATOR_TEST_PATH}/.env", "/path/with/$/and/$1"]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        application.working_directory,
+        Some(PathBuf::from("/path/to/src"))
+    );
+    assert_eq!(
+        application.env_file,
+        vec![
+            PathBuf::from("/path/to/.env"),
+            PathBuf::from("/path/with/$/and/$1"),
+        ]
+    );
+
+    let general = toml::from_str::<General>(r#"layout = "$HOME/layout.json""#).unwrap();
+    assert_eq!(
+        general.layout,
+        Some(Layout::Path(
+            dirs_next::home_dir().unwrap().join("layout.json")
+        ))
+    );
+}
+
+#[test]
+fn path_expansion_errors() {
+    env::remove_var("I3NATOR_TEST_UNSET");
+
+    let error = toml::from_str::<Application>(
+        r#"command = "mycommand"
+           working_directory = "$I3NATOR_TEST_UNSET/src""#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("environment variable 'I3NATOR_TEST_UNSET' is not set"));
+
+    let error = toml::from_str::<Application>(
+        r#"command = "mycommand"
+           working_directory = "${HOME""#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("unterminated variable reference"));
+
+    let error = toml::from_str::<Application>(
+        r#"command = "mycommand"
+           working_directory = "~i3nator-nonexistent-user/src""#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("user 'i3nator-nonexistent-user' doesn't exist"));
+}
+
+#[test]
+fn general_extends() {
+    let general = toml::from_str::<General>(r#"extends = "base""#).unwrap();
+    assert_eq!(
+        general.extends,
+        Some(Extends {
+            name: "base".to_owned(),
+            applications: Inheritance::Append,
+        })
+    );
+
+    let general =
+        toml::from_str::<General>(r#"extends = { name = "base", applications = "replace" }"#)
+            .unwrap();
+    assert_eq!(
+        general.extends,
+        Some(Extends {
+            name: "base".to_owned(),
+            applications: Inheritance::Replace,
+        })
+    );
+
+    assert!(toml::from_str::<General>(r#"extends = { applications = "replace" }"#).is_err());
+}
+
+#[test]
+fn include() {
+    let config = toml::from_str::<Config>(
+        r#"[general]
+           layout = "{ ... }"
+
+           [[include]]
+           path = "~/fragments/htop.toml""#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.include,
+        vec![Include {
+            path: Fragment::Path(dirs_next::home_dir().unwrap().join("fragments/htop.toml")),
+        }]
+    );
+
+    assert!(toml::from_str::<Include>(r#"fragment = "htop""#).is_err());
+}
+
+#[test]
+fn general_hooks() {
+    let general = toml::from_str::<General>(
+        r#"on_start = "git fetch"
+           after_start = ["notify-send 'project started'", ["ssh-add"]]
+           on_stop = { commands = ["docker compose down"], timeout = 120 }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        general.on_start,
+        Some(Hooks {
+            commands: vec![ApplicationCommand {
+                program: "git".to_owned(),
+                args: vec!["fetch".to_owned()],
+                commandline: None,
+            }],
+            timeout: Duration::from_secs(60),
+            shell: None,
+        })
+    );
+    assert_eq!(
+        general.after_start,
+        Some(Hooks {
+            commands: vec![
+                ApplicationCommand {
+                    program: "notify-send".to_owned(),
+                    args: vec!["project started".to_owned()],
+                    commandline: None,
+                },
+                ApplicationCommand {
+                    program: "ssh-add".to_owned(),
+                    args: vec![],
+                    commandline: None,
+                },
+            ],
+            timeout: Duration::from_secs(60),
+            shell: None,
+        })
+    );
+    assert_eq!(
+        general.on_stop,
+        Some(Hooks {
+            commands: vec![ApplicationCommand {
+                program: "docker".to_owned(),
+                args: vec!["compose".to_owned(), "down".to_owned()],
+                commandline: None,
+            }],
+            timeout: Duration::from_secs(120),
+            shell: None,
+        })
+    );
+
+    assert!(toml::from_str::<General>(r#"on_start = [""]"#).is_err());
+}
+
+#[test]
+fn application_name() {
+    let application =
+        toml::from_str::<Application>(r#"command = "/usr/bin/urxvt -e htop""#).unwrap();
+    assert_eq!(application.name, None);
+    assert_eq!(application.name(), "urxvt");
+
+    let application = toml::from_str::<Application>(
+        r#"name = "monitor"
+           command = "/usr/bin/urxvt -e htop""#,
+    )
+    .unwrap();
+    assert_eq!(application.name(), "monitor");
+}
+
+#[test]
+fn application_readiness() {
+    let application = toml::from_str::<Application>(
+        r#"name = "server"
+           command = "mycommand"
+           depends_on = ["database"]
+           wait_for_window = false
+           wait_for_port = 8080
+           wait_for_file = "/tmp/server.sock"
+           ready_timeout = 60
+           delay = 1"#,
+    )
+    .unwrap();
+    assert_eq!(application.name, Some("server".to_owned()));
+    assert_eq!(application.depends_on, vec!["database"]);
+    assert!(!application.wait_for_window);
+    assert_eq!(application.wait_for_port, Some("localhost:8080".to_owned()));
+    assert_eq!(
+        application.wait_for_file,
+        Some(PathBuf::from("/tmp/server.sock"))
+    );
+    assert_eq!(application.ready_timeout, Duration::from_secs(60));
+    assert_eq!(application.delay, Duration::from_secs(1));
+
+    let application = toml::from_str::<Application>(
+        r#"command = "mycommand"
+           wait_for_port = "127.0.0.1:8080""#,
+    )
+    .unwrap();
+    assert_eq!(application.wait_for_port, Some("127.0.0.1:8080".to_owned()));
+
+    for invalid in &[r#"wait_for_port = 0"#, r#"wait_for_port = "8080""#] {
+        assert!(
+            toml::from_str::<Application>(&format!("command = \"mycommand\"\n{}", invalid))
+                .is_err()
+        );
+    }
+}
+
+#[test]
+fn application_waits_for_window() {
+    let application = |extra: &str| {
+        toml::from_str::<Application>(&format!("command = \"mycommand\"\n{}", extra)).unwrap()
+    };
+
+    // All applications wait for their window by default
+    assert!(application("").waits_for_window());
+    assert!(application(r#"exec = "echo hi""#).waits_for_window());
+
+    assert!(application("wait_for_window = true").waits_for_window());
+    assert!(!application("window_timeout = 0").waits_for_window());
+    assert!(!application("wait_for_window = false").waits_for_window());
+    assert!(!application("wait_for_window = false\nexec = \"echo hi\"").waits_for_window());
+}
+
+#[test]
+fn application_shell() {
+    let application = toml::from_str::<Application>(r#"command = "make | tee build.log""#).unwrap();
+    assert_eq!(application.shell, None);
+    assert_eq!(
+        application.command.commandline,
+        Some("make | tee build.log".to_owned())
+    );
+
+    let application = toml::from_str::<Application>(
+        r#"command = "make | tee build.log"
+           shell = true"#,
+    )
+    .unwrap();
+    assert_eq!(application.shell, Some(PathBuf::from("/bin/sh")));
+
+    let application = toml::from_str::<Application>(
+        r#"command = "make | tee build.log"
+           shell = "/bin/zsh""#,
+    )
+    .unwrap();
+    assert_eq!(application.shell, Some(PathBuf::from("/bin/zsh")));
+
+    let application = toml::from_str::<Application>(
+        r#"command = "make"
+           shell = false"#,
+    )
+    .unwrap();
+    assert_eq!(application.shell, None);
+
+    let general = toml::from_str::<General>(
+        r#"on_start = { commands = ["git fetch && git status"], shell = true }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        general.on_start.unwrap().shell,
+        Some(PathBuf::from("/bin/sh"))
+    );
+}
+
 #[test]
 fn duration_secs() {
     equivalent! {
@@ -182,13 +798,56 @@ fn duration_secs() {
            timeout = 10"#,
         Exec {
             commands: vec![],
+            steps: vec![],
             exec_type: ExecType::Text,
+            backend: ExecBackend::X11,
+            target: None,
+            wait_for: WaitFor::default(),
+            paste: Paste::default(),
             timeout: Duration::from_secs(10),
         };
         Exec
     }
 }
 
+#[test]
+fn duration_float() {
+    equivalent! {
+        r#"commands = []
+           timeout = 2.5"#,
+        Exec {
+            commands: vec![],
+            steps: vec![],
+            exec_type: ExecType::Text,
+            backend: ExecBackend::X11,
+            target: None,
+            wait_for: WaitFor::default(),
+            paste: Paste::default(),
+            timeout: Duration::from_millis(2500),
+        };
+        Exec
+    }
+
+    for invalid in &[
+        "-1",
+        "-2.5",
+        "86401",
+        "86400.5",
+        "1e19",
+        "1e30",
+        "18446744073709551616.0",
+        "nan",
+        "inf",
+        "{ secs = 86401, nanos = 0 }",
+    ] {
+        assert!(
+            toml::from_str::<Exec>(&format!("commands = []\ntimeout = {}", invalid)).is_err(),
+            "expected timeout = {} to be rejected",
+            invalid
+        );
+    }
+}
+
 #[test]
 fn duration_map() {
     equivalent! {
@@ -196,7 +855,12 @@ fn duration_map() {
            timeout = { secs = 10, nanos = 42 }"#,
         Exec {
             commands: vec![],
+            steps: vec![],
             exec_type: ExecType::Text,
+            backend: ExecBackend::X11,
+            target: None,
+            wait_for: WaitFor::default(),
+            paste: Paste::default(),
             timeout: Duration::new(10, 42),
         };
         Exec
@@ -219,7 +883,12 @@ fn duration_str() {
 fn exec_commands_only() {
     let expected = Exec {
         commands: vec!["command one".to_owned(), "command two".to_owned()],
+        steps: vec![],
         exec_type: ExecType::Text,
+        backend: ExecBackend::X11,
+        target: None,
+        wait_for: WaitFor::default(),
+        paste: Paste::default(),
         timeout: Duration::from_secs(5),
     };
 
@@ -234,7 +903,12 @@ fn exec_commands_only() {
 fn exec_commands_and_type() {
     let expected = Exec {
         commands: vec!["command one".to_owned(), "command two".to_owned()],
+        steps: vec![],
         exec_type: ExecType::TextNoReturn,
+        backend: ExecBackend::X11,
+        target: None,
+        wait_for: WaitFor::default(),
+        paste: Paste::default(),
         timeout: Duration::from_secs(5),
     };
 
@@ -252,7 +926,12 @@ fn exec_commands_and_type() {
 fn exec_commands_type_and_timeout() {
     let expected = Exec {
         commands: vec!["command one".to_owned(), "command two".to_owned()],
+        steps: vec![],
         exec_type: ExecType::TextNoReturn,
+        backend: ExecBackend::X11,
+        target: None,
+        wait_for: WaitFor::default(),
+        paste: Paste::default(),
         timeout: Duration::from_secs(10),
     };
 
@@ -267,19 +946,140 @@ fn exec_commands_type_and_timeout() {
     }
 }
 
+#[test]
+fn exec_wait_for() {
+    let exec = toml::from_str::<Exec>(
+        r#"
+            commands = ["command one", "command two"]
+            wait_for = { title = '\$ $', delay = 1, between = 0.5 }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        exec.wait_for,
+        WaitFor {
+            title: Some("\\$ $".to_owned()),
+            delay: Duration::from_secs(1),
+            between: Duration::from_millis(500),
+        }
+    );
+
+    for invalid in &["title = '('", "unknown = 1"] {
+        assert!(
+            toml::from_str::<Exec>(&format!("commands = []\nwait_for = {{ {} }}", invalid))
+                .is_err()
+        );
+    }
+}
+
+#[test]
+fn exec_paste() {
+    let exec = toml::from_str::<Exec>(
+        r#"
+            commands = ["command one"]
+            exec_type = "paste"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(exec.exec_type, ExecType::Paste);
+    assert_eq!(
+        exec.paste,
+        Paste {
+            selection: PasteSelection::Clipboard,
+            keys: "ctrl+shift+v".to_owned(),
+        }
+    );
+
+    let exec = toml::from_str::<Exec>(
+        r#"
+            commands = ["command one"]
+            exec_type = "paste"
+            paste = { selection = "primary", keys = "shift+Insert" }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        exec.paste,
+        Paste {
+            selection: PasteSelection::Primary,
+            keys: "shift+Insert".to_owned(),
+        }
+    );
+}
+
+#[test]
+fn exec_backend() {
+    let expected = Exec {
+        commands: vec!["command one".to_owned()],
+        steps: vec![],
+        exec_type: ExecType::Text,
+        backend: ExecBackend::Xdotool,
+        target: None,
+        wait_for: WaitFor::default(),
+        paste: Paste::default(),
+        timeout: Duration::from_secs(5),
+    };
+
+    equivalent! {
+        r#"
+            commands = ["command one"]
+            backend = "xdotool"
+        "#,
+        expected;
+        Exec
+    }
+
+    let exec = toml::from_str::<Exec>(
+        r#"
+            commands = ["command one"]
+            backend = "tmux"
+            target = "editor"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(exec.backend, ExecBackend::Tmux);
+    assert_eq!(exec.target, Some("editor".to_owned()));
+
+    assert!(toml::from_str::<Exec>(
+        r#"
+            commands = ["command one"]
+            backend = "unknown"
+        "#
+    )
+    .is_err());
+}
+
 #[test]
 fn exec_str() {
     let expected = Application {
         command: ApplicationCommand {
             program: "-".to_owned(),
             args: vec![],
+            commandline: None,
         },
         working_directory: None,
         exec: Some(Exec {
             commands: vec!["command one".to_owned()],
+            steps: vec![],
             exec_type: ExecType::Text,
+            backend: ExecBackend::X11,
+            target: None,
+            wait_for: WaitFor::default(),
+            paste: Paste::default(),
             timeout: Duration::from_secs(5),
         }),
+        window_timeout: Duration::from_secs(5),
+        env: BTreeMap::new(),
+        unset_env: vec![],
+        env_file: vec![],
+        name: None,
+        depends_on: vec![],
+        wait_for_window: true,
+        wait_for_port: None,
+        wait_for_file: None,
+        ready_timeout: Duration::from_secs(30),
+        delay: Duration::from_secs(0),
+        shell: None,
     };
 
     equivalent! {
@@ -298,13 +1098,31 @@ fn exec_seq() {
         command: ApplicationCommand {
             program: "-".to_owned(),
             args: vec![],
+            commandline: None,
         },
         working_directory: None,
         exec: Some(Exec {
             commands: vec!["command one".to_owned(), "command two".to_owned()],
+            steps: vec![],
             exec_type: ExecType::Text,
+            backend: ExecBackend::X11,
+            target: None,
+            wait_for: WaitFor::default(),
+            paste: Paste::default(),
             timeout: Duration::from_secs(5),
         }),
+        window_timeout: Duration::from_secs(5),
+        env: BTreeMap::new(),
+        unset_env: vec![],
+        env_file: vec![],
+        name: None,
+        depends_on: vec![],
+        wait_for_window: true,
+        wait_for_port: None,
+        wait_for_file: None,
+        ready_timeout: Duration::from_secs(30),
+        delay: Duration::from_secs(0),
+        shell: None,
     };
 
     equivalent! {
@@ -316,3 +1134,68 @@ fn exec_seq() {
         Application
     }
 }
+
+#[test]
+fn exec_steps() {
+    let application = toml::from_str::<Application>(
+        r#"
+            command = "-"
+            exec = [
+                { keys = ["ctrl+r"] },
+                { text = "git log" },
+                { keys = ["Return"] },
+                { sleep = 1.5 },
+                { wait_title = "^vim" },
+                { paste = "echo ✓" },
+            ]
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        application.exec,
+        Some(Exec {
+            commands: vec![],
+            steps: vec![
+                ExecStep::Keys(vec!["ctrl+r".to_owned()]),
+                ExecStep::Text("git log".to_owned()),
+                ExecStep::Keys(vec!["Return".to_owned()]),
+                ExecStep::Sleep(Duration::from_millis(1500)),
+                ExecStep::WaitTitle("^vim".to_owned()),
+                ExecStep::Paste("echo ✓".to_owned()),
+            ],
+            exec_type: ExecType::Text,
+            backend: ExecBackend::X11,
+            target: None,
+            wait_for: WaitFor::default(),
+            paste: Paste::default(),
+            timeout: Duration::from_secs(5),
+        })
+    );
+
+    let application = toml::from_str::<Application>(
+        r#"
+            command = "-"
+            exec = { steps = [{ text = "ls" }], backend = "tmux", target = "editor" }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        application.exec.unwrap().steps,
+        vec![ExecStep::Text("ls".to_owned())]
+    );
+
+    for invalid in &[
+        r#"[{ text = "ls" }, "ls"]"#,
+        r#"[{ unknown = "ls" }]"#,
+        r#"[{ text = "ls", keys = ["Return"] }]"#,
+        r#"[{ wait_title = "(" }]"#,
+        r#"[]"#,
+        r#"{ commands = [] }"#,
+        r#"{ steps = [], backend = "tmux", target = "editor" }"#,
+        r#"{ exec_type = "keys" }"#,
+    ] {
+        assert!(
+            toml::from_str::<Application>(&format!("command = \"-\"\nexec = {}", invalid)).is_err()
+        );
+    }
+}